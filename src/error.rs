@@ -0,0 +1,165 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::LndError;
+
+/// gRPC status codes, as reported by LND in the `code` field of its errors.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum GrpcStatus {
+    Ok,
+    Cancelled,
+    Unknown,
+    InvalidArgument,
+    DeadlineExceeded,
+    NotFound,
+    AlreadyExists,
+    PermissionDenied,
+    ResourceExhausted,
+    FailedPrecondition,
+    Aborted,
+    OutOfRange,
+    Unimplemented,
+    Internal,
+    Unavailable,
+    DataLoss,
+    Unauthenticated,
+}
+impl GrpcStatus {
+    pub fn code(&self) -> i32 {
+        match self {
+            GrpcStatus::Ok => 0,
+            GrpcStatus::Cancelled => 1,
+            GrpcStatus::Unknown => 2,
+            GrpcStatus::InvalidArgument => 3,
+            GrpcStatus::DeadlineExceeded => 4,
+            GrpcStatus::NotFound => 5,
+            GrpcStatus::AlreadyExists => 6,
+            GrpcStatus::PermissionDenied => 7,
+            GrpcStatus::ResourceExhausted => 8,
+            GrpcStatus::FailedPrecondition => 9,
+            GrpcStatus::Aborted => 10,
+            GrpcStatus::OutOfRange => 11,
+            GrpcStatus::Unimplemented => 12,
+            GrpcStatus::Internal => 13,
+            GrpcStatus::Unavailable => 14,
+            GrpcStatus::DataLoss => 15,
+            GrpcStatus::Unauthenticated => 16,
+        }
+    }
+}
+impl From<i32> for GrpcStatus {
+    fn from(value: i32) -> Self {
+        match value {
+            0 => GrpcStatus::Ok,
+            1 => GrpcStatus::Cancelled,
+            3 => GrpcStatus::InvalidArgument,
+            4 => GrpcStatus::DeadlineExceeded,
+            5 => GrpcStatus::NotFound,
+            6 => GrpcStatus::AlreadyExists,
+            7 => GrpcStatus::PermissionDenied,
+            8 => GrpcStatus::ResourceExhausted,
+            9 => GrpcStatus::FailedPrecondition,
+            10 => GrpcStatus::Aborted,
+            11 => GrpcStatus::OutOfRange,
+            12 => GrpcStatus::Unimplemented,
+            13 => GrpcStatus::Internal,
+            14 => GrpcStatus::Unavailable,
+            15 => GrpcStatus::DataLoss,
+            16 => GrpcStatus::Unauthenticated,
+            _ => GrpcStatus::Unknown,
+        }
+    }
+}
+impl Display for GrpcStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum BrightError {
+    #[cfg(not(target_arch = "wasm32"))]
+    #[error("Transport error: {0}")]
+    Transport(#[from] reqwest::Error),
+    #[cfg(not(target_arch = "wasm32"))]
+    #[error("Websocket error: {0}")]
+    Websocket(#[from] Box<tokio_tungstenite::tungstenite::Error>),
+    #[cfg(not(target_arch = "wasm32"))]
+    #[error("TLS error: {0}")]
+    Tls(#[from] native_tls::Error),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Permission denied: {}", .0.message())]
+    Auth(LndError),
+    #[error("LND error {}: {}", .0.status(), .0.message())]
+    Lnd(LndError),
+    #[error("Decode error: {0}")]
+    Decode(#[from] serde_json::Error),
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+    #[error("LNURL error: {0}")]
+    Lnurl(String),
+}
+impl BrightError {
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        BrightError::InvalidInput(message.into())
+    }
+    /// gRPC status of the error, if it was reported by LND.
+    pub fn status(&self) -> Option<GrpcStatus> {
+        match self {
+            BrightError::Lnd(error) | BrightError::Auth(error) => Some(error.status()),
+            _ => None,
+        }
+    }
+}
+impl From<LndError> for BrightError {
+    fn from(value: LndError) -> Self {
+        match value.status() {
+            GrpcStatus::PermissionDenied | GrpcStatus::Unauthenticated => BrightError::Auth(value),
+            _ => BrightError::Lnd(value),
+        }
+    }
+}
+#[cfg(not(target_arch = "wasm32"))]
+impl From<tokio_tungstenite::tungstenite::Error> for BrightError {
+    fn from(value: tokio_tungstenite::tungstenite::Error) -> Self {
+        BrightError::Websocket(Box::new(value))
+    }
+}
+
+pub type BrightResult<T> = Result<T, BrightError>;
+
+#[cfg(test)]
+mod test {
+    use super::{BrightError, GrpcStatus};
+    use crate::LndError;
+
+    #[test]
+    fn maps_lnd_error_codes() {
+        let error = LndError::try_from(
+            r#"{"error":{"code":5,"message":"unable to locate invoice"}}"#.to_string(),
+        )
+        .unwrap();
+        assert_eq!(error.status(), GrpcStatus::NotFound);
+        let error = BrightError::from(error);
+        assert_eq!(error.status(), Some(GrpcStatus::NotFound));
+        assert_eq!(
+            error.to_string(),
+            "LND error NotFound: unable to locate invoice"
+        );
+
+        let denied = BrightError::from(LndError::new(
+            GrpcStatus::Unauthenticated,
+            "verification failed: signature mismatch",
+        ));
+        assert!(matches!(denied, BrightError::Auth(_)));
+        assert_eq!(denied.status(), Some(GrpcStatus::Unauthenticated));
+        assert_eq!(
+            denied.to_string(),
+            "Permission denied: verification failed: signature mismatch"
+        );
+        assert_eq!(GrpcStatus::from(42), GrpcStatus::Unknown);
+        assert_eq!(GrpcStatus::AlreadyExists.code(), 6);
+    }
+}
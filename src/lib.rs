@@ -1,4 +1,8 @@
+mod error;
 mod ln_address;
 mod lnd;
+#[cfg(all(not(target_arch = "wasm32"), any(test, feature = "testing")))]
+pub mod testing;
+pub use error::*;
 pub use ln_address::*;
 pub use lnd::*;
@@ -1,6 +1,25 @@
-use base64::prelude::*;
-use lightning_invoice::Bolt11Invoice;
 use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+use crate::{Bolt11PaymentRequest, BrightError, BrightResult, PaymentHash};
+
+/// LNURL services answer failures with `{"status": "ERROR", "reason": "..."}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct LnUrlErrorResponse {
+    status: String,
+    reason: Option<String>,
+}
+fn lnurl_response<T>(value: &str) -> BrightResult<T>
+where
+    T: serde::de::DeserializeOwned,
+{
+    if let Ok(error) = serde_json::from_str::<LnUrlErrorResponse>(value) {
+        if error.status.eq_ignore_ascii_case("ERROR") {
+            return Err(BrightError::Lnurl(error.reason.unwrap_or_default()));
+        }
+    }
+    Ok(serde_json::from_str(value)?)
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct LnAddressPaymentRequest {
@@ -9,46 +28,45 @@ pub struct LnAddressPaymentRequest {
 impl LnAddressPaymentRequest {
     #[cfg(not(target_arch = "wasm32"))]
     pub async fn new(
-        address: &LightningAddress,
+        address: &LightningAddress<'_>,
         millisatoshis: u64,
         client: &reqwest::Client,
-    ) -> anyhow::Result<Self> {
+    ) -> BrightResult<Self> {
         let confirmation = LnAddressConfirmation::new(address, client).await?;
         tracing::info!("Confirmation: {:?}", confirmation);
         if millisatoshis < confirmation.min_sendable {
-            return Err(anyhow::anyhow!("Amount too low"));
+            return Err(BrightError::invalid_input(format!(
+                "Amount below minimum of {} msats",
+                confirmation.min_sendable
+            )));
+        }
+        if millisatoshis > confirmation.max_sendable {
+            return Err(BrightError::invalid_input(format!(
+                "Amount above maximum of {} msats",
+                confirmation.max_sendable
+            )));
         }
         let pr_url = format!("{}?amount={}", confirmation.callback, millisatoshis);
         let pay_request_fetch = client.get(&pr_url).send().await?.text().await?;
         tracing::debug!("Pay request: {}", pay_request_fetch);
-        Ok(LnAddressPaymentRequest::try_from(pay_request_fetch)?)
+        LnAddressPaymentRequest::try_from(pay_request_fetch)
     }
-    pub fn r_hash(&self) -> anyhow::Result<String> {
-        let r_hash_b = self
-            .pr
-            .parse::<Bolt11Invoice>()
-            .map_err(|e| anyhow::anyhow!(e.to_string()))?;
-        let r_hash = BASE64_STANDARD.encode(r_hash_b.payment_hash());
-        Ok(r_hash)
+    pub fn r_hash(&self) -> BrightResult<PaymentHash> {
+        self.decode()?.payment_hash()
     }
-    pub fn r_hash_url_safe(&self) -> anyhow::Result<String> {
-        let r_hash = self
-            .pr
-            .parse::<Bolt11Invoice>()
-            .map_err(|e| anyhow::anyhow!(e.to_string()))?;
-        let url_safe = BASE64_URL_SAFE.encode(r_hash.payment_hash());
-        Ok(url_safe)
+    pub fn r_hash_url_safe(&self) -> BrightResult<String> {
+        Ok(self.r_hash()?.to_base64_url())
     }
 }
-impl ToString for LnAddressPaymentRequest {
-    fn to_string(&self) -> String {
-        serde_json::to_string(self).unwrap()
+impl Display for LnAddressPaymentRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", serde_json::to_string(self).unwrap())
     }
 }
 impl TryFrom<String> for LnAddressPaymentRequest {
-    type Error = anyhow::Error;
+    type Error = BrightError;
     fn try_from(value: String) -> Result<Self, Self::Error> {
-        Ok(serde_json::from_str(&value)?)
+        lnurl_response(&value)
     }
 }
 
@@ -62,33 +80,39 @@ pub struct LnAddressConfirmation {
 }
 impl LnAddressConfirmation {
     #[cfg(not(target_arch = "wasm32"))]
-    pub async fn new(address: &LightningAddress, client: &reqwest::Client) -> anyhow::Result<Self> {
-        let (user, domain) = address.0.split_once('@').ok_or_else(|| anyhow::anyhow!("Invalid address"))?;
+    pub async fn new(
+        address: &LightningAddress<'_>,
+        client: &reqwest::Client,
+    ) -> BrightResult<Self> {
+        let (user, domain) = address
+            .0
+            .split_once('@')
+            .ok_or_else(|| BrightError::invalid_input("Invalid address"))?;
         let url = format!("https://{}/.well-known/lnurlp/{}", domain, user);
         let response = client.get(&url).send().await?.text().await?;
         LnAddressConfirmation::try_from(response)
     }
 }
-impl ToString for LnAddressConfirmation {
-    fn to_string(&self) -> String {
-        serde_json::to_string(self).unwrap()
+impl Display for LnAddressConfirmation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", serde_json::to_string(self).unwrap())
     }
 }
 impl TryFrom<String> for LnAddressConfirmation {
-    type Error = anyhow::Error;
+    type Error = BrightError;
     fn try_from(value: String) -> Result<Self, Self::Error> {
-        Ok(serde_json::from_str(&value)?)
+        lnurl_response(&value)
     }
 }
 
-pub struct LightningAddress(pub &'static str);
-impl LightningAddress {
+pub struct LightningAddress<'a>(pub &'a str);
+impl LightningAddress<'_> {
     #[cfg(not(target_arch = "wasm32"))]
     pub async fn get_invoice(
         &self,
         client: &reqwest::Client,
         millisatoshis: u64,
-    ) -> anyhow::Result<LnAddressPaymentRequest> {
+    ) -> BrightResult<LnAddressPaymentRequest> {
         LnAddressPaymentRequest::new(self, millisatoshis, client).await
     }
 }
@@ -98,13 +122,29 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn lnurl_error_response() {
+        let error = LnAddressConfirmation::try_from(
+            r#"{"status":"ERROR","reason":"Unknown user"}"#.to_string(),
+        );
+        assert!(matches!(error, Err(BrightError::Lnurl(reason)) if reason == "Unknown user"));
+        let confirmation = LnAddressConfirmation::try_from(
+            r#"{"callback":"https://example.com/cb","minSendable":1000,"maxSendable":2000,"tag":"payRequest"}"#
+                .to_string(),
+        );
+        assert!(confirmation.is_ok());
+    }
+
     #[tokio::test]
     #[tracing_test::traced_test]
     pub async fn get_ln_url_invoice() -> Result<(), anyhow::Error> {
-        let client = reqwest::Client::new();
-        let address = LightningAddress("42pupusas@blink.sv");
-        let invoice = address.get_invoice(&client, 1000).await?;
+        let lnd = crate::testing::MockLnd::start().await;
+        let address = lnd.lightning_address("42pupusas");
+        let invoice = LightningAddress(&address)
+            .get_invoice(&lnd.client().client, 1000)
+            .await?;
         tracing::info!("Invoice: {:?}", invoice);
+        assert!(invoice.r_hash().is_ok());
         Ok(())
     }
 }
@@ -0,0 +1,220 @@
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use reqwest::header::{HeaderMap, HeaderValue};
+
+use super::{InvoiceStore, LightningClient, LndConnectUri, LndTlsConfig};
+use crate::{BrightError, BrightResult};
+
+#[derive(Debug, Clone)]
+enum MacaroonSource {
+    Bytes(Vec<u8>),
+    Hex(String),
+    Path(PathBuf),
+}
+impl MacaroonSource {
+    fn into_hex(self) -> BrightResult<String> {
+        match self {
+            MacaroonSource::Bytes(bytes) => {
+                Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+            }
+            MacaroonSource::Hex(hex) => {
+                let hex = hex.trim().to_lowercase();
+                if hex.is_empty()
+                    || hex.len() % 2 != 0
+                    || !hex.chars().all(|c| c.is_ascii_hexdigit())
+                {
+                    return Err(BrightError::invalid_input("Invalid macaroon hex"));
+                }
+                Ok(hex)
+            }
+            MacaroonSource::Path(path) => {
+                let bytes = std::fs::read(&path)?;
+                MacaroonSource::Bytes(bytes).into_hex()
+            }
+        }
+    }
+}
+
+/// Configures and builds a [`LightningClient`].
+///
+/// The macaroon is loaded once at build time and kept in memory, so it can come
+/// from a file, an environment variable or a secret store.
+#[derive(Debug, Clone, Default)]
+pub struct LightningClientBuilder {
+    host: Option<String>,
+    port: Option<u16>,
+    macaroon: Option<MacaroonSource>,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    user_agent: Option<String>,
+    tls: LndTlsConfig,
+    tls_cert_path: Option<PathBuf>,
+    invoice_store: Option<Arc<dyn InvoiceStore>>,
+}
+impl LightningClientBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Host of the LND REST interface, optionally including the port (`host:port`).
+    pub fn host(mut self, host: impl Into<String>) -> Self {
+        self.host = Some(host.into());
+        self
+    }
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+    pub fn macaroon_bytes(mut self, macaroon: impl Into<Vec<u8>>) -> Self {
+        self.macaroon = Some(MacaroonSource::Bytes(macaroon.into()));
+        self
+    }
+    pub fn macaroon_hex(mut self, macaroon: impl Into<String>) -> Self {
+        self.macaroon = Some(MacaroonSource::Hex(macaroon.into()));
+        self
+    }
+    pub fn macaroon_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.macaroon = Some(MacaroonSource::Path(path.into()));
+        self
+    }
+    /// Total timeout applied to every REST request.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+    pub fn tls(mut self, tls: LndTlsConfig) -> Self {
+        self.tls = tls;
+        self.tls_cert_path = None;
+        self
+    }
+    /// Pins LND's `tls.cert`, read from disk at build time.
+    pub fn tls_cert_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.tls_cert_path = Some(path.into());
+        self
+    }
+    /// Pins LND's `tls.cert` from its PEM contents.
+    pub fn tls_cert_pem(self, cert_pem: impl Into<Vec<u8>>) -> Self {
+        self.tls(LndTlsConfig::pinned(cert_pem))
+    }
+    /// Disables certificate validation for both REST and websocket connections.
+    pub fn danger_accept_invalid_certs(self) -> Self {
+        self.tls(LndTlsConfig::danger_accept_invalid_certs())
+    }
+    /// Store the client writes its invoices and hodl preimages through to.
+    pub fn invoice_store(mut self, store: impl InvoiceStore + 'static) -> Self {
+        self.invoice_store = Some(Arc::new(store));
+        self
+    }
+    /// Takes host, port, macaroon and pinned certificate from an lndconnect URI.
+    pub fn lnd_connect(self, uri: &LndConnectUri) -> Self {
+        let tls = uri.tls();
+        let builder = self
+            .host(uri.host.clone())
+            .macaroon_bytes(uri.macaroon.clone());
+        let builder = match uri.port {
+            Some(port) => builder.port(port),
+            None => builder,
+        };
+        builder.tls(tls)
+    }
+    pub fn build(self) -> BrightResult<LightningClient> {
+        let host = self
+            .host
+            .ok_or_else(|| BrightError::invalid_input("Missing host"))?;
+        let url = match self.port {
+            Some(port) => format!("{}:{}", host, port),
+            None => host,
+        };
+        let macaroon = self.macaroon.map(MacaroonSource::into_hex).transpose()?;
+        let tls = match self.tls_cert_path {
+            Some(path) => LndTlsConfig::from_cert_path(path)?,
+            None => self.tls,
+        };
+
+        let mut default_header = HeaderMap::new();
+        if let Some(macaroon) = &macaroon {
+            let mut header_value = HeaderValue::from_str(macaroon)
+                .map_err(|e| BrightError::invalid_input(e.to_string()))?;
+            header_value.set_sensitive(true);
+            default_header.insert("Grpc-Metadata-macaroon", header_value);
+        }
+        default_header.insert("Accept", HeaderValue::from_static("application/json"));
+        default_header.insert("Content-Type", HeaderValue::from_static("application/json"));
+        let mut client =
+            tls.configure_reqwest(reqwest::Client::builder().default_headers(default_header))?;
+        if let Some(timeout) = self.timeout {
+            client = client.timeout(timeout);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            client = client.connect_timeout(timeout);
+        }
+        if let Some(user_agent) = self.user_agent {
+            client = client.user_agent(user_agent);
+        }
+        Ok(LightningClient::from_parts(
+            url,
+            macaroon.unwrap_or_default(),
+            tls,
+            client.build()?,
+            self.invoice_store,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::LightningClientBuilder;
+
+    #[test]
+    fn macaroon_sources_match() -> anyhow::Result<()> {
+        let bytes = vec![0x02, 0x01, 0xab, 0xff];
+        let path = std::env::temp_dir().join("bright_lightning_builder_test.macaroon");
+        std::fs::write(&path, &bytes)?;
+        let from_bytes = LightningClientBuilder::new()
+            .host("localhost")
+            .port(8080)
+            .macaroon_bytes(bytes)
+            .build()?;
+        let from_hex = LightningClientBuilder::new()
+            .host("localhost:8080")
+            .macaroon_hex("0201ABFF")
+            .build()?;
+        let from_path = LightningClientBuilder::new()
+            .host("localhost:8080")
+            .macaroon_path(&path)
+            .build()?;
+        std::fs::remove_file(&path)?;
+        assert_eq!(from_bytes.macaroon(), "0201abff");
+        assert_eq!(from_hex.macaroon(), "0201abff");
+        assert_eq!(from_path.macaroon(), "0201abff");
+        assert_eq!(from_bytes.url(), "localhost:8080");
+        assert_eq!(from_hex.url(), "localhost:8080");
+        Ok(())
+    }
+    #[test]
+    fn rejects_bad_config() {
+        assert!(LightningClientBuilder::new().build().is_err());
+        assert!(LightningClientBuilder::new()
+            .host("localhost")
+            .macaroon_hex("not hex")
+            .build()
+            .is_err());
+        assert!(LightningClientBuilder::new()
+            .host("localhost")
+            .macaroon_path("./does-not-exist.macaroon")
+            .build()
+            .is_err());
+        assert!(LightningClientBuilder::new()
+            .host("localhost")
+            .tls_cert_path("./does-not-exist.cert")
+            .build()
+            .is_err());
+    }
+}
@@ -0,0 +1,643 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures_util::{
+    stream::{self, BoxStream},
+    Stream, StreamExt, TryStreamExt,
+};
+use tokio::time::{Interval, MissedTickBehavior};
+
+use super::{LightningClient, LndWebsocket};
+use crate::{
+    BrightError, BrightResult, ListInvoicesQuery, LndHodlInvoice, LndHodlInvoiceRequestBody,
+    LndInvoice, LndInvoiceState, PaymentHash, Preimage,
+};
+
+const DEFAULT_CANCEL_MARGIN: u32 = 12;
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Creates a hodl invoice, generating its preimage locally unless one is given.
+///
+/// Created with [`LightningClient::hodl_invoice`].
+#[derive(Clone)]
+pub struct HodlInvoiceBuilder {
+    client: LightningClient,
+    preimage: Option<Preimage>,
+    payment_hash: Option<PaymentHash>,
+    body: LndHodlInvoiceRequestBody,
+}
+impl HodlInvoiceBuilder {
+    /// Preimage to lock the invoice to instead of a random one.
+    pub fn preimage(mut self, preimage: Preimage) -> Self {
+        self.preimage = Some(preimage);
+        self
+    }
+    /// Hash of a preimage held elsewhere, which must then be passed to
+    /// [`HodlInvoiceHandle::settle_with`].
+    pub fn payment_hash(mut self, payment_hash: PaymentHash) -> Self {
+        self.payment_hash = Some(payment_hash);
+        self
+    }
+    pub fn memo(mut self, memo: impl Into<String>) -> Self {
+        self.body.memo = Some(memo.into());
+        self
+    }
+    /// Amount in satoshis. Cannot be combined with [`Self::value_msat`].
+    pub fn value(mut self, sat: u64) -> Self {
+        self.body.value = sat;
+        self
+    }
+    /// Amount in millisatoshis. Cannot be combined with [`Self::value`].
+    pub fn value_msat(mut self, msat: u64) -> Self {
+        self.body.value_msat = msat;
+        self
+    }
+    /// Seconds until the invoice expires, LND defaults to 24 hours.
+    pub fn expiry(mut self, seconds: u64) -> Self {
+        self.body.expiry = seconds;
+        self
+    }
+    /// CLTV delta of the final hop, which bounds how long an accepted payment
+    /// can be held.
+    pub fn cltv_expiry(mut self, blocks: u64) -> Self {
+        self.body.cltv_expiry = blocks;
+        self
+    }
+    /// SHA256 of the description committed to by the invoice.
+    pub fn description_hash(mut self, hash: [u8; 32]) -> Self {
+        self.body.description_hash = hash.to_vec();
+        self
+    }
+    /// Include route hints for private channels.
+    pub fn private(mut self, private: bool) -> Self {
+        self.body.private = private;
+        self
+    }
+    pub async fn create(self) -> BrightResult<HodlInvoiceHandle> {
+        let preimage = match (self.preimage, self.payment_hash) {
+            (Some(preimage), Some(hash)) if !preimage.matches(&hash) => {
+                return Err(BrightError::invalid_input(
+                    "Preimage does not match the payment hash",
+                ));
+            }
+            (Some(preimage), _) => Some(preimage),
+            (None, Some(_)) => None,
+            (None, None) => Some(Preimage::random()?),
+        };
+        let payment_hash = match preimage {
+            Some(preimage) => preimage.payment_hash(),
+            None => self.payment_hash.unwrap_or_default(),
+        };
+        let mut body = self.body;
+        body.hash = payment_hash;
+        let invoice = self.client.create_hodl_invoice(&body, preimage).await?;
+        Ok(HodlInvoiceHandle {
+            client: self.client,
+            invoice,
+            payment_hash,
+            preimage,
+        })
+    }
+}
+
+/// Hodl invoice created by a [`HodlInvoiceBuilder`].
+#[derive(Clone)]
+pub struct HodlInvoiceHandle {
+    client: LightningClient,
+    invoice: LndHodlInvoice,
+    payment_hash: PaymentHash,
+    preimage: Option<Preimage>,
+}
+impl HodlInvoiceHandle {
+    pub fn invoice(&self) -> &LndHodlInvoice {
+        &self.invoice
+    }
+    pub fn payment_request(&self) -> String {
+        self.invoice.payment_request()
+    }
+    pub fn payment_hash(&self) -> PaymentHash {
+        self.payment_hash
+    }
+    /// Preimage of the invoice, unless it was created from a payment hash.
+    pub fn preimage(&self) -> Option<Preimage> {
+        self.preimage
+    }
+    /// Current state of the invoice.
+    pub async fn lookup(&self) -> BrightResult<LndInvoice> {
+        self.client.lookup_invoice_by_hash(&self.payment_hash).await
+    }
+    /// Updates of the invoice's state.
+    pub async fn subscribe(&self) -> BrightResult<LndWebsocket> {
+        self.client.subscribe_to_invoice(&self.payment_hash).await
+    }
+    /// Settles the accepted payment with the invoice's own preimage.
+    pub async fn settle(&self) -> BrightResult<()> {
+        let preimage = self
+            .preimage
+            .ok_or_else(|| BrightError::invalid_input("Invoice was created without a preimage"))?;
+        self.settle_with(&preimage).await
+    }
+    /// Settles the accepted payment, checking `preimage` against the payment hash
+    /// before it is sent to LND.
+    pub async fn settle_with(&self, preimage: &Preimage) -> BrightResult<()> {
+        if !preimage.matches(&self.payment_hash) {
+            return Err(BrightError::invalid_input(
+                "Preimage does not match the payment hash",
+            ));
+        }
+        self.client.settle_htlc(preimage).await
+    }
+    /// Cancels the invoice, failing any accepted payment back to the payer.
+    pub async fn cancel(&self) -> BrightResult<()> {
+        self.client.cancel_htlc(&self.payment_hash).await
+    }
+}
+
+/// Change in the state of an invoice tracked by a [`HodlInvoiceManager`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HodlEvent {
+    /// A payment is held and waits to be settled or canceled.
+    Accepted(LndInvoice),
+    Settled(LndInvoice),
+    Canceled(LndInvoice),
+    /// The manager canceled the invoice as its HTLC came within the cancel
+    /// margin of its CLTV expiry at `block_height`.
+    ExpiryCanceled {
+        invoice: LndInvoice,
+        block_height: u32,
+    },
+}
+impl HodlEvent {
+    pub fn invoice(&self) -> &LndInvoice {
+        match self {
+            HodlEvent::Accepted(invoice)
+            | HodlEvent::Settled(invoice)
+            | HodlEvent::Canceled(invoice)
+            | HodlEvent::ExpiryCanceled { invoice, .. } => invoice,
+        }
+    }
+}
+
+/// Watches a set of hodl invoices and cancels held payments before their HTLCs
+/// expire, as an HTLC held past its CLTV expiry forces the channel closed.
+///
+/// Settlements come from the client's invoice subscription, filtered to the
+/// tracked invoices. LND's subscription leaves out accepted and canceled
+/// invoices, so every interval also lists the pending invoices once, and reads
+/// the block height from `getinfo` while a payment is held. Invoices are
+/// dropped once settled or canceled.
+#[derive(Clone)]
+pub struct HodlInvoiceManager {
+    client: LightningClient,
+    tracked: Arc<Mutex<HashMap<PaymentHash, LndInvoiceState>>>,
+    cancel_margin: u32,
+    poll_interval: Duration,
+}
+impl HodlInvoiceManager {
+    pub fn new(client: LightningClient) -> Self {
+        Self {
+            client,
+            tracked: Arc::new(Mutex::new(HashMap::new())),
+            cancel_margin: DEFAULT_CANCEL_MARGIN,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+        }
+    }
+    /// Blocks before the CLTV expiry of an accepted HTLC at which its invoice is
+    /// canceled.
+    pub fn cancel_margin(mut self, blocks: u32) -> Self {
+        self.cancel_margin = blocks;
+        self
+    }
+    /// Time between two checks of the pending invoices and the block height,
+    /// which is also how long a failed subscription waits before it is reopened.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+    pub fn track(&self, payment_hash: PaymentHash) {
+        self.tracked
+            .lock()
+            .unwrap()
+            .entry(payment_hash)
+            .or_default();
+    }
+    pub fn untrack(&self, payment_hash: &PaymentHash) {
+        self.tracked.lock().unwrap().remove(payment_hash);
+    }
+    /// Tracks the pending hodl invoices of the client's invoice store, as after a
+    /// restart. Returns how many were found.
+    pub fn resume(&self) -> BrightResult<usize> {
+        let Some(store) = self.client.invoice_store() else {
+            return Ok(0);
+        };
+        let pending = store
+            .pending()?
+            .into_iter()
+            .filter(|invoice| invoice.hodl)
+            .collect::<Vec<_>>();
+        for invoice in &pending {
+            self.track(invoice.payment_hash);
+        }
+        Ok(pending.len())
+    }
+    pub fn tracked(&self) -> Vec<PaymentHash> {
+        self.tracked.lock().unwrap().keys().copied().collect()
+    }
+    /// Events of the tracked invoices, including those tracked after the stream
+    /// was created. Errors concern a single invoice and are yielded without
+    /// ending the stream.
+    pub fn events(&self) -> impl Stream<Item = BrightResult<HodlEvent>> + Send + 'static {
+        let mut interval = tokio::time::interval(self.poll_interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let watch = HodlWatch {
+            manager: self.clone(),
+            invoices: None,
+            accepted: HashMap::new(),
+            interval,
+            buffered: VecDeque::new(),
+        };
+        stream::unfold(watch, |mut watch| async move {
+            let event = watch.next().await;
+            Some((event, watch))
+        })
+    }
+}
+
+type InvoiceStream = BoxStream<'static, BrightResult<LndInvoice>>;
+
+/// State of a [`HodlInvoiceManager::events`] stream.
+struct HodlWatch {
+    manager: HodlInvoiceManager,
+    /// Invoice subscription of the client, reopened at the next interval after
+    /// it ended with an error.
+    invoices: Option<InvoiceStream>,
+    /// Held invoices, checked against the block height at every interval.
+    accepted: HashMap<PaymentHash, LndInvoice>,
+    interval: Interval,
+    buffered: VecDeque<BrightResult<HodlEvent>>,
+}
+impl HodlWatch {
+    async fn next(&mut self) -> BrightResult<HodlEvent> {
+        loop {
+            if let Some(event) = self.buffered.pop_front() {
+                return event;
+            }
+            let subscribed = self.invoices.is_some();
+            let (invoices, interval) = (&mut self.invoices, &mut self.interval);
+            let update = tokio::select! {
+                Some(update) = async { invoices.as_mut()?.next().await }, if subscribed => {
+                    Some(update)
+                }
+                _ = interval.tick() => None,
+            };
+            match update {
+                Some(Ok(invoice)) => self.update(invoice),
+                Some(Err(e)) => {
+                    self.invoices = None;
+                    self.buffered.push_back(Err(e));
+                }
+                None => {
+                    if self.invoices.is_none() {
+                        self.invoices = Some(self.manager.client.subscribe_invoices(0, 0).boxed());
+                    }
+                    self.check_pending().await;
+                    self.cancel_expiring().await;
+                }
+            }
+        }
+    }
+    /// Reads the state of the tracked invoices still pending, as LND's invoice
+    /// subscription only reports invoices being added and settled.
+    async fn check_pending(&mut self) {
+        let tracked = self.manager.tracked.lock().unwrap().clone();
+        self.accepted.retain(|hash, _| tracked.contains_key(hash));
+        if tracked.is_empty() {
+            return;
+        }
+        let pending = self
+            .manager
+            .client
+            .list_invoices_stream(ListInvoicesQuery::new().pending_only(true))
+            .try_filter(|invoice| std::future::ready(tracked.contains_key(&invoice.r_hash)))
+            .try_collect::<Vec<_>>()
+            .await;
+        let pending = match pending {
+            Ok(pending) => pending,
+            Err(e) => {
+                self.buffered.push_back(Err(e));
+                return;
+            }
+        };
+        // Tracked invoices no longer pending were settled or canceled.
+        for hash in tracked.keys() {
+            if pending.iter().any(|invoice| invoice.r_hash == *hash) {
+                continue;
+            }
+            match self.manager.client.lookup_invoice_by_hash(hash).await {
+                Ok(invoice) => self.update(invoice),
+                Err(e) => self.buffered.push_back(Err(e)),
+            }
+        }
+        for invoice in pending {
+            self.update(invoice);
+        }
+    }
+    fn update(&mut self, invoice: LndInvoice) {
+        let hash = invoice.r_hash;
+        let state = invoice.state.clone();
+        let Some(previous) = self.manager.tracked.lock().unwrap().get(&hash).cloned() else {
+            return;
+        };
+        match state {
+            LndInvoiceState::Accepted => {
+                self.accepted.insert(hash, invoice.clone());
+            }
+            _ => {
+                self.accepted.remove(&hash);
+            }
+        }
+        if previous == state {
+            return;
+        }
+        match state {
+            LndInvoiceState::Open => {}
+            LndInvoiceState::Accepted => self.buffered.push_back(Ok(HodlEvent::Accepted(invoice))),
+            LndInvoiceState::Settled => self.buffered.push_back(Ok(HodlEvent::Settled(invoice))),
+            LndInvoiceState::Canceled => self.buffered.push_back(Ok(HodlEvent::Canceled(invoice))),
+        }
+        if let Err(e) = self.manager.client.record_state(&hash, state.clone()) {
+            self.buffered.push_back(Err(e));
+        }
+        let mut tracked = self.manager.tracked.lock().unwrap();
+        match state {
+            LndInvoiceState::Settled | LndInvoiceState::Canceled => {
+                tracked.remove(&hash);
+            }
+            state => {
+                if let Some(entry) = tracked.get_mut(&hash) {
+                    *entry = state;
+                }
+            }
+        }
+    }
+    /// Cancels the held invoices whose HTLCs are within the cancel margin of
+    /// their CLTV expiry.
+    async fn cancel_expiring(&mut self) {
+        if self.accepted.is_empty() {
+            return;
+        }
+        let block_height = match self.manager.client.get_info().await {
+            Ok(info) => info.block_height(),
+            Err(e) => {
+                self.buffered.push_back(Err(e));
+                return;
+            }
+        };
+        let cancel_height = block_height.saturating_add(self.manager.cancel_margin);
+        let expiring = self
+            .accepted
+            .iter()
+            .filter(|(_, invoice)| {
+                invoice
+                    .accepted_expiry_height()
+                    .is_some_and(|height| height <= cancel_height)
+            })
+            .map(|(hash, _)| *hash)
+            .collect::<Vec<_>>();
+        for hash in expiring {
+            if let Err(e) = self.manager.client.cancel_htlc(&hash).await {
+                self.buffered.push_back(Err(e));
+                continue;
+            }
+            tracing::warn!("Canceled hodl invoice {} at CLTV expiry", hash);
+            self.manager.untrack(&hash);
+            if let Some(invoice) = self.accepted.remove(&hash) {
+                self.buffered.push_back(Ok(HodlEvent::ExpiryCanceled {
+                    invoice,
+                    block_height,
+                }));
+            }
+        }
+    }
+}
+
+impl LightningClient {
+    pub fn hodl_invoice(&self) -> HodlInvoiceBuilder {
+        HodlInvoiceBuilder {
+            client: self.clone(),
+            preimage: None,
+            payment_hash: None,
+            body: LndHodlInvoiceRequestBody::default(),
+        }
+    }
+    pub fn hodl_manager(&self) -> HodlInvoiceManager {
+        HodlInvoiceManager::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use futures_util::{Stream, StreamExt};
+
+    use super::HodlEvent;
+    use crate::{
+        testing::MockLnd, BrightError, BrightResult, GrpcStatus, LndInvoiceState, PaymentHash,
+        Preimage,
+    };
+
+    #[tokio::test]
+    async fn hodl_invoice_settles_with_its_preimage() -> anyhow::Result<()> {
+        let lnd = MockLnd::start().await;
+        let client = lnd.client();
+        let hodl = client
+            .hodl_invoice()
+            .value(2100)
+            .memo("Hodl")
+            .cltv_expiry(80)
+            .private(true)
+            .create()
+            .await?;
+        let preimage = hodl.preimage().unwrap();
+        assert_eq!(preimage.payment_hash(), hodl.payment_hash());
+        assert_eq!(hodl.invoice().payment_hash(), hodl.payment_hash());
+
+        let invoice = hodl.lookup().await?;
+        assert_eq!(invoice.memo.as_deref(), Some("Hodl"));
+        assert_eq!(invoice.value, 2100);
+        assert_eq!(invoice.cltv_expiry, 80);
+        assert!(invoice.private);
+        assert_eq!(invoice.state, LndInvoiceState::Open);
+
+        lnd.simulate_payment(&hodl.payment_hash())?;
+        assert_eq!(hodl.lookup().await?.state, LndInvoiceState::Accepted);
+        assert!(matches!(
+            hodl.settle_with(&Preimage::from_bytes([7; 32])).await,
+            Err(BrightError::InvalidInput(_))
+        ));
+        hodl.settle().await?;
+        let settled = hodl.lookup().await?;
+        assert_eq!(settled.state, LndInvoiceState::Settled);
+        assert_eq!(settled.r_preimage, Some(preimage));
+        Ok(())
+    }
+    #[tokio::test]
+    async fn hodl_invoice_from_external_hash() -> anyhow::Result<()> {
+        let lnd = MockLnd::start().await;
+        let client = lnd.client();
+        let preimage = Preimage::from_bytes([9; 32]);
+        let hodl = client
+            .hodl_invoice()
+            .payment_hash(preimage.payment_hash())
+            .value_msat(500_000)
+            .create()
+            .await?;
+        assert!(hodl.preimage().is_none());
+        assert!(matches!(
+            hodl.settle().await,
+            Err(BrightError::InvalidInput(_))
+        ));
+        hodl.cancel().await?;
+        assert_eq!(hodl.lookup().await?.state, LndInvoiceState::Canceled);
+
+        let mismatched = client
+            .hodl_invoice()
+            .preimage(preimage)
+            .payment_hash(PaymentHash::default())
+            .create()
+            .await;
+        assert!(matches!(mismatched, Err(BrightError::InvalidInput(_))));
+        assert!(client
+            .hodl_invoice()
+            .value(1)
+            .value_msat(1000)
+            .create()
+            .await
+            .is_err());
+        assert!(client
+            .hodl_invoice()
+            .value(u64::MAX)
+            .create()
+            .await
+            .is_err());
+        Ok(())
+    }
+    async fn next_event(
+        events: &mut (impl Stream<Item = BrightResult<HodlEvent>> + Unpin),
+    ) -> anyhow::Result<HodlEvent> {
+        Ok(tokio::time::timeout(Duration::from_secs(5), events.next())
+            .await?
+            .unwrap()?)
+    }
+    async fn no_event(events: &mut (impl Stream<Item = BrightResult<HodlEvent>> + Unpin)) -> bool {
+        tokio::time::timeout(Duration::from_millis(100), events.next())
+            .await
+            .is_err()
+    }
+
+    #[tokio::test]
+    async fn manager_cancels_before_cltv_expiry() -> anyhow::Result<()> {
+        let lnd = MockLnd::start().await;
+        let client = lnd.client();
+        let manager = client
+            .hodl_manager()
+            .cancel_margin(10)
+            .poll_interval(Duration::from_millis(20));
+        let first = client
+            .hodl_invoice()
+            .value(100)
+            .cltv_expiry(40)
+            .create()
+            .await?;
+        let second = client
+            .hodl_invoice()
+            .value(200)
+            .cltv_expiry(40)
+            .create()
+            .await?;
+        manager.track(first.payment_hash());
+        manager.track(second.payment_hash());
+        let mut events = Box::pin(manager.events());
+        assert!(no_event(&mut events).await);
+
+        lnd.simulate_payment(&first.payment_hash())?;
+        lnd.simulate_payment(&second.payment_hash())?;
+        for _ in 0..2 {
+            assert!(matches!(
+                next_event(&mut events).await?,
+                HodlEvent::Accepted(_)
+            ));
+        }
+        assert!(no_event(&mut events).await);
+
+        first.settle().await?;
+        match next_event(&mut events).await? {
+            HodlEvent::Settled(invoice) => assert_eq!(invoice.r_hash, first.payment_hash()),
+            other => panic!("Expected settlement, got {:?}", other),
+        }
+        assert_eq!(manager.tracked(), vec![second.payment_hash()]);
+
+        lnd.mine_blocks(29);
+        assert!(no_event(&mut events).await);
+        lnd.mine_blocks(1);
+        match next_event(&mut events).await? {
+            HodlEvent::ExpiryCanceled {
+                invoice,
+                block_height,
+            } => {
+                assert_eq!(invoice.r_hash, second.payment_hash());
+                assert_eq!(block_height, lnd.block_height());
+            }
+            other => panic!("Expected expiry cancel, got {:?}", other),
+        }
+        assert_eq!(
+            lnd.invoice_state(&second.payment_hash()),
+            Some(LndInvoiceState::Canceled)
+        );
+        assert!(manager.tracked().is_empty());
+        assert!(no_event(&mut events).await);
+        Ok(())
+    }
+    #[tokio::test]
+    async fn manager_streams_events() -> anyhow::Result<()> {
+        let lnd = MockLnd::start().await;
+        let client = lnd.client();
+        let manager = client
+            .hodl_manager()
+            .poll_interval(Duration::from_millis(20));
+        let hodl = client.hodl_invoice().value(100).create().await?;
+        manager.track(hodl.payment_hash());
+        let mut events = Box::pin(manager.events());
+
+        lnd.simulate_payment(&hodl.payment_hash())?;
+        let accepted = next_event(&mut events).await?;
+        assert!(matches!(accepted, HodlEvent::Accepted(_)));
+
+        // Tracked while streaming, with an unknown invoice failing on its own.
+        let unknown = PaymentHash::from_bytes([5; 32]);
+        manager.track(unknown);
+        let later = client.hodl_invoice().value(300).create().await?;
+        manager.track(later.payment_hash());
+        assert_eq!(
+            events.next().await.unwrap().err().and_then(|e| e.status()),
+            Some(GrpcStatus::NotFound)
+        );
+        manager.untrack(&unknown);
+
+        // Still watched after the node dropped the subscription.
+        lnd.drop_subscriptions();
+        hodl.cancel().await?;
+        let canceled = next_event(&mut events).await?;
+        assert!(matches!(canceled, HodlEvent::Canceled(_)));
+        assert_eq!(canceled.invoice().r_hash, hodl.payment_hash());
+        lnd.simulate_payment(&later.payment_hash())?;
+        let accepted = next_event(&mut events).await?;
+        assert_eq!(accepted.invoice().r_hash, later.payment_hash());
+        assert_eq!(manager.tracked(), vec![later.payment_hash()]);
+        Ok(())
+    }
+}
@@ -0,0 +1,148 @@
+use std::{fmt::Display, str::FromStr};
+
+use base64::{
+    alphabet,
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+    Engine,
+};
+
+use super::LndTlsConfig;
+use crate::BrightError;
+
+const LNDCONNECT_BASE64: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::URL_SAFE,
+    GeneralPurposeConfig::new()
+        .with_encode_padding(false)
+        .with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Connection string in the `lndconnect://host:port?cert=...&macaroon=...` format
+/// used by Zeus and Thunderhub.
+///
+/// The certificate is the DER encoding of LND's `tls.cert` and the macaroon is the
+/// raw macaroon, both base64url encoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LndConnectUri {
+    pub host: String,
+    pub port: Option<u16>,
+    pub cert: Option<Vec<u8>>,
+    pub macaroon: Vec<u8>,
+}
+impl LndConnectUri {
+    pub fn url(&self) -> String {
+        match self.port {
+            Some(port) => format!("{}:{}", self.host, port),
+            None => self.host.clone(),
+        }
+    }
+    pub fn tls(&self) -> LndTlsConfig {
+        match &self.cert {
+            Some(cert) => LndTlsConfig::pinned_der(cert),
+            None => LndTlsConfig::default(),
+        }
+    }
+}
+impl FromStr for LndConnectUri {
+    type Err = BrightError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let uri =
+            url::Url::parse(s.trim()).map_err(|e| BrightError::invalid_input(e.to_string()))?;
+        if uri.scheme() != "lndconnect" {
+            return Err(BrightError::invalid_input("Invalid lndconnect scheme"));
+        }
+        let host = uri
+            .host_str()
+            .filter(|host| !host.is_empty())
+            .ok_or_else(|| BrightError::invalid_input("Missing lndconnect host"))?
+            .to_string();
+        let mut cert = None;
+        let mut macaroon = None;
+        for (key, value) in uri.query_pairs() {
+            let decode = || {
+                LNDCONNECT_BASE64.decode(value.as_bytes()).map_err(|e| {
+                    BrightError::invalid_input(format!("Invalid lndconnect {key}: {e}"))
+                })
+            };
+            match key.as_ref() {
+                "cert" => cert = Some(decode()?),
+                "macaroon" => macaroon = Some(decode()?),
+                _ => {}
+            }
+        }
+        Ok(Self {
+            host,
+            port: uri.port(),
+            cert,
+            macaroon: macaroon
+                .ok_or_else(|| BrightError::invalid_input("Missing lndconnect macaroon"))?,
+        })
+    }
+}
+impl TryFrom<String> for LndConnectUri {
+    type Error = BrightError;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+impl Display for LndConnectUri {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "lndconnect://{}?", self.url())?;
+        if let Some(cert) = &self.cert {
+            write!(f, "cert={}&", LNDCONNECT_BASE64.encode(cert))?;
+        }
+        write!(f, "macaroon={}", LNDCONNECT_BASE64.encode(&self.macaroon))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::LndConnectUri;
+    use crate::LightningClient;
+
+    #[test]
+    fn parse_and_serialize() -> anyhow::Result<()> {
+        let uri = "lndconnect://lnd.example.com:8080?cert=MIIB_w&macaroon=AgEDbG5k";
+        let parsed: LndConnectUri = uri.parse()?;
+        assert_eq!(parsed.host, "lnd.example.com");
+        assert_eq!(parsed.port, Some(8080));
+        assert_eq!(parsed.cert, Some(vec![0x30, 0x82, 0x01, 0xff]));
+        assert_eq!(parsed.macaroon, vec![0x02, 0x01, 0x03, 0x6c, 0x6e, 0x64]);
+        assert_eq!(parsed.to_string(), uri);
+        assert_eq!(parsed.to_string().parse::<LndConnectUri>()?, parsed);
+
+        let no_cert: LndConnectUri = "lndconnect://10.0.0.1?macaroon=AgEDbG5k".parse()?;
+        assert_eq!(no_cert.url(), "10.0.0.1");
+        assert!(no_cert.cert.is_none());
+        Ok(())
+    }
+    #[test]
+    fn rejects_invalid_uris() {
+        assert!("https://lnd.example.com:8080?macaroon=AgEDbG5k"
+            .parse::<LndConnectUri>()
+            .is_err());
+        assert!("lndconnect://lnd.example.com:8080?cert=MIIB_w"
+            .parse::<LndConnectUri>()
+            .is_err());
+        assert!("lndconnect://lnd.example.com:8080?macaroon=%%%"
+            .parse::<LndConnectUri>()
+            .is_err());
+    }
+    #[test]
+    fn pins_der_certificate() -> anyhow::Result<()> {
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()])?;
+        let uri = LndConnectUri {
+            host: "localhost".to_string(),
+            port: Some(8080),
+            cert: Some(cert.cert.der().to_vec()),
+            macaroon: vec![0x02, 0x01],
+        };
+        let tls = uri.tls();
+        let pinned = native_tls::Certificate::from_pem(tls.cert_pem().unwrap())?;
+        assert_eq!(pinned.to_der()?, cert.cert.der().to_vec());
+
+        let client = LightningClient::from_lndconnect(&uri.to_string())?;
+        assert_eq!(client.url(), "localhost:8080");
+        assert_eq!(client.macaroon(), "0201");
+        Ok(())
+    }
+}
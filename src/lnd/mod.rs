@@ -5,6 +5,32 @@ mod websocket;
 #[cfg(not(target_arch = "wasm32"))]
 pub use websocket::*;
 #[cfg(not(target_arch = "wasm32"))]
+mod tls;
+#[cfg(not(target_arch = "wasm32"))]
+pub use tls::*;
+#[cfg(not(target_arch = "wasm32"))]
+mod lnd_connect;
+#[cfg(not(target_arch = "wasm32"))]
+pub use lnd_connect::*;
+#[cfg(not(target_arch = "wasm32"))]
+mod client_builder;
+#[cfg(not(target_arch = "wasm32"))]
+pub use client_builder::*;
+#[cfg(not(target_arch = "wasm32"))]
 mod rest_client;
 #[cfg(not(target_arch = "wasm32"))]
 pub use rest_client::*;
+#[cfg(not(target_arch = "wasm32"))]
+mod hodl;
+#[cfg(not(target_arch = "wasm32"))]
+mod subscriptions;
+#[cfg(not(target_arch = "wasm32"))]
+pub use hodl::*;
+#[cfg(not(target_arch = "wasm32"))]
+mod payments;
+#[cfg(not(target_arch = "wasm32"))]
+pub use payments::*;
+#[cfg(not(target_arch = "wasm32"))]
+mod store;
+#[cfg(not(target_arch = "wasm32"))]
+pub use store::*;
@@ -0,0 +1,145 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt::Display};
+
+use super::string_int;
+use crate::BrightError;
+
+/// Amount reported by LND in both satoshis and millisatoshis.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct LndAmount {
+    #[serde(with = "string_int")]
+    pub sat: u64,
+    #[serde(with = "string_int")]
+    pub msat: u64,
+}
+
+/// Lightning liquidity across all open channels, as returned by `/v1/balance/channels`.
+///
+/// The local balance is what the node can spend, the remote balance what it can receive.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(default)]
+pub struct LndChannelBalance {
+    pub local_balance: LndAmount,
+    pub remote_balance: LndAmount,
+    pub unsettled_local_balance: LndAmount,
+    pub unsettled_remote_balance: LndAmount,
+    pub pending_open_local_balance: LndAmount,
+    pub pending_open_remote_balance: LndAmount,
+}
+impl TryFrom<String> for LndChannelBalance {
+    type Error = BrightError;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Ok(serde_json::from_str(&value)?)
+    }
+}
+impl TryInto<String> for LndChannelBalance {
+    type Error = BrightError;
+    fn try_into(self) -> Result<String, Self::Error> {
+        Ok(serde_json::to_string(&self)?)
+    }
+}
+impl Display for LndChannelBalance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", serde_json::to_string_pretty(self).unwrap())
+    }
+}
+
+/// On-chain balance of a single wallet account.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(default)]
+pub struct LndWalletAccountBalance {
+    #[serde(with = "string_int")]
+    pub confirmed_balance: i64,
+    #[serde(with = "string_int")]
+    pub unconfirmed_balance: i64,
+}
+
+/// On-chain wallet balance in satoshis, as returned by `/v1/balance/blockchain`.
+///
+/// `account_balance` is keyed by the account names used in
+/// [`crate::LndListAddressesResponse::find_addresses`], e.g. `default`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct LndWalletBalance {
+    #[serde(with = "string_int")]
+    pub total_balance: i64,
+    #[serde(with = "string_int")]
+    pub confirmed_balance: i64,
+    #[serde(with = "string_int")]
+    pub unconfirmed_balance: i64,
+    #[serde(with = "string_int")]
+    pub locked_balance: i64,
+    #[serde(with = "string_int")]
+    pub reserved_balance_anchor_chan: i64,
+    pub account_balance: HashMap<String, LndWalletAccountBalance>,
+}
+impl LndWalletBalance {
+    pub fn account(&self, account_name: &str) -> Option<&LndWalletAccountBalance> {
+        self.account_balance.get(account_name)
+    }
+}
+impl TryFrom<String> for LndWalletBalance {
+    type Error = BrightError;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Ok(serde_json::from_str(&value)?)
+    }
+}
+impl TryInto<String> for LndWalletBalance {
+    type Error = BrightError;
+    fn try_into(self) -> Result<String, Self::Error> {
+        Ok(serde_json::to_string(&self)?)
+    }
+}
+impl Display for LndWalletBalance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", serde_json::to_string_pretty(self).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{LndAmount, LndChannelBalance, LndWalletBalance};
+
+    #[test]
+    fn decodes_channel_balance() -> anyhow::Result<()> {
+        let balance = LndChannelBalance::try_from(
+            r#"{"balance":"1500","pending_open_balance":"0",
+                "local_balance":{"sat":"1500","msat":"1500123"},
+                "remote_balance":{"sat":"3000","msat":"3000000"},
+                "unsettled_local_balance":{"sat":"10","msat":"10000"}}"#
+                .to_string(),
+        )?;
+        assert_eq!(
+            balance.local_balance,
+            LndAmount {
+                sat: 1500,
+                msat: 1500123
+            }
+        );
+        assert_eq!(balance.remote_balance.sat, 3000);
+        assert_eq!(balance.unsettled_local_balance.msat, 10000);
+        assert_eq!(balance.pending_open_remote_balance, LndAmount::default());
+
+        let json: String = balance.clone().try_into()?;
+        assert!(json.contains(r#""msat":"1500123""#));
+        assert_eq!(LndChannelBalance::try_from(json)?, balance);
+        Ok(())
+    }
+    #[test]
+    fn decodes_wallet_balance() -> anyhow::Result<()> {
+        let balance = LndWalletBalance::try_from(
+            r#"{"total_balance":"120000","confirmed_balance":"100000",
+                "unconfirmed_balance":"20000","locked_balance":"5000",
+                "reserved_balance_anchor_chan":"10000",
+                "account_balance":{"default":{"confirmed_balance":"100000","unconfirmed_balance":"20000"}}}"#
+                .to_string(),
+        )?;
+        assert_eq!(balance.total_balance, 120_000);
+        assert_eq!(balance.locked_balance, 5_000);
+        let default = balance.account("default").unwrap();
+        assert_eq!(default.confirmed_balance, 100_000);
+        assert_eq!(default.unconfirmed_balance, 20_000);
+        assert!(balance.account("missing").is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,67 @@
+//! Serde helpers for the `bytes` fields LND encodes as base64 strings.
+//!
+//! Use with `#[serde(with = "base64_bytes")]`. Deserializing accepts both the standard
+//! and the URL safe alphabets, with or without padding.
+use base64::prelude::*;
+use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+pub(crate) fn decode(value: &str) -> Result<Vec<u8>, base64::DecodeError> {
+    let trimmed = value.trim_end_matches('=');
+    BASE64_STANDARD_NO_PAD
+        .decode(trimmed)
+        .or_else(|_| BASE64_URL_SAFE_NO_PAD.decode(trimmed))
+}
+
+pub(crate) fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: AsRef<[u8]>,
+    S: Serializer,
+{
+    serializer.serialize_str(&BASE64_STANDARD.encode(value))
+}
+
+pub(crate) fn deserialize<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Option::<String>::deserialize(deserializer)?.unwrap_or_default();
+    decode(&value).map_err(D::Error::custom)
+}
+
+/// Maps of `bytes` values keyed by integers, such as TLV custom records.
+///
+/// Works with any map type, `null` reads as an empty map.
+pub(crate) mod map {
+    use std::collections::HashMap;
+
+    use base64::prelude::*;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub(crate) fn serialize<'a, M, S>(value: &'a M, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        &'a M: IntoIterator<Item = (&'a u64, &'a Vec<u8>)>,
+        S: Serializer,
+    {
+        serializer.collect_map(
+            value
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), BASE64_STANDARD.encode(value))),
+        )
+    }
+
+    pub(crate) fn deserialize<'de, M, D>(deserializer: D) -> Result<M, D::Error>
+    where
+        M: FromIterator<(u64, Vec<u8>)>,
+        D: Deserializer<'de>,
+    {
+        Option::<HashMap<String, String>>::deserialize(deserializer)?
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(key, value)| {
+                let key = key.parse().map_err(D::Error::custom)?;
+                let value = super::decode(&value).map_err(D::Error::custom)?;
+                Ok((key, value))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,320 @@
+use std::{collections::HashMap, fmt::Display, str::FromStr, time::Duration};
+
+use lightning_invoice::{Bolt11Invoice, Bolt11InvoiceDescription, Currency};
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{
+    base64_bytes, bytes32, string_int, LndFeature, LndHopHint, LndInvoice, LndPaymentInvoice,
+    LndRouteHint, PaymentHash,
+};
+use crate::{BrightError, BrightResult, LnAddressPaymentRequest};
+
+/// Copies out a digest the invoice library hands out as a byte slice.
+fn digest_bytes(digest: &[u8]) -> BrightResult<[u8; 32]> {
+    bytes32(digest)
+}
+
+/// BOLT11 payment request decoded locally.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DecodedInvoice(Bolt11Invoice);
+impl DecodedInvoice {
+    pub fn bolt11(&self) -> &Bolt11Invoice {
+        &self.0
+    }
+    pub fn payment_request(&self) -> String {
+        self.0.to_string()
+    }
+    /// Amount in millisatoshis, `None` for amountless invoices.
+    pub fn amount_msat(&self) -> Option<u64> {
+        self.0.amount_milli_satoshis()
+    }
+    pub fn payment_hash(&self) -> BrightResult<PaymentHash> {
+        digest_bytes(&self.0.payment_hash()[..]).map(PaymentHash::from_bytes)
+    }
+    /// Payment secret of the invoice, LND's `payment_addr`.
+    pub fn payment_secret(&self) -> [u8; 32] {
+        self.0.payment_secret().0
+    }
+    /// Memo of the invoice, `None` when it commits to a description hash instead.
+    pub fn description(&self) -> Option<String> {
+        match self.0.description() {
+            Bolt11InvoiceDescription::Direct(description) => Some(description.to_string()),
+            Bolt11InvoiceDescription::Hash(_) => None,
+        }
+    }
+    pub fn description_hash(&self) -> BrightResult<Option<[u8; 32]>> {
+        match self.0.description() {
+            Bolt11InvoiceDescription::Hash(hash) => digest_bytes(&hash.0[..]).map(Some),
+            Bolt11InvoiceDescription::Direct(_) => Ok(None),
+        }
+    }
+    /// Unix timestamp at which the invoice was created.
+    pub fn timestamp(&self) -> u64 {
+        self.0.duration_since_epoch().as_secs()
+    }
+    pub fn expiry(&self) -> Duration {
+        self.0.expiry_time()
+    }
+    /// Unix timestamp at which the invoice expires.
+    pub fn expires_at(&self) -> u64 {
+        self.0
+            .duration_since_epoch()
+            .saturating_add(self.expiry())
+            .as_secs()
+    }
+    /// Whether the invoice has expired by the unix timestamp `now`.
+    pub fn is_expired_at(&self, now: u64) -> bool {
+        self.expires_at() <= now
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn is_expired(&self) -> bool {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or_default();
+        self.is_expired_at(now)
+    }
+    /// CLTV delta the final hop requires.
+    pub fn min_final_cltv_expiry_delta(&self) -> u64 {
+        self.0.min_final_cltv_expiry_delta()
+    }
+    /// Route hints for private channels, in LND's format.
+    pub fn route_hints(&self) -> Vec<LndRouteHint> {
+        self.0
+            .route_hints()
+            .into_iter()
+            .map(|hint| LndRouteHint {
+                hop_hints: hint
+                    .0
+                    .into_iter()
+                    .map(|hop| LndHopHint {
+                        node_id: hop.src_node_id.to_string(),
+                        chan_id: hop.short_channel_id,
+                        fee_base_msat: hop.fees.base_msat,
+                        fee_proportional_millionths: hop.fees.proportional_millionths,
+                        cltv_expiry_delta: hop.cltv_expiry_delta as u32,
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+    /// Feature bits set in the invoice.
+    pub fn features(&self) -> Vec<u32> {
+        let Some(features) = self.0.features() else {
+            return vec![];
+        };
+        features
+            .le_flags()
+            .iter()
+            .enumerate()
+            .flat_map(|(byte, flags)| {
+                (0..8)
+                    .filter(move |bit| flags & (1 << bit) != 0)
+                    .map(move |bit| (byte * 8 + bit) as u32)
+            })
+            .collect()
+    }
+    /// Network of the invoice, named as in LND's `getinfo`.
+    pub fn network(&self) -> &'static str {
+        match self.0.currency() {
+            Currency::Bitcoin => "mainnet",
+            Currency::BitcoinTestnet => "testnet",
+            Currency::Regtest => "regtest",
+            Currency::Simnet => "simnet",
+            Currency::Signet => "signet",
+        }
+    }
+    /// Hex encoded public key of the node that issued the invoice.
+    pub fn payee_pubkey(&self) -> String {
+        self.0.get_payee_pub_key().to_string()
+    }
+}
+impl FromStr for DecodedInvoice {
+    type Err = BrightError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.trim()
+            .parse::<Bolt11Invoice>()
+            .map(Self)
+            .map_err(|e| BrightError::invalid_input(format!("Invalid payment request: {e}")))
+    }
+}
+impl From<Bolt11Invoice> for DecodedInvoice {
+    fn from(value: Bolt11Invoice) -> Self {
+        Self(value)
+    }
+}
+impl Display for DecodedInvoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+impl Serialize for DecodedInvoice {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&self.0)
+    }
+}
+impl<'de> Deserialize<'de> for DecodedInvoice {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+/// Models carrying a BOLT11 payment request.
+pub trait Bolt11PaymentRequest {
+    fn payment_request_str(&self) -> &str;
+    fn decode(&self) -> BrightResult<DecodedInvoice> {
+        self.payment_request_str().parse()
+    }
+}
+impl Bolt11PaymentRequest for LnAddressPaymentRequest {
+    fn payment_request_str(&self) -> &str {
+        &self.pr
+    }
+}
+impl Bolt11PaymentRequest for LndPaymentInvoice {
+    fn payment_request_str(&self) -> &str {
+        &self.payment_request
+    }
+}
+impl Bolt11PaymentRequest for LndInvoice {
+    fn payment_request_str(&self) -> &str {
+        &self.payment_request
+    }
+}
+/// Payment request as decoded by LND on `/v1/payreq/{pay_req}`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct LndPayReq {
+    pub destination: String,
+    pub payment_hash: PaymentHash,
+    #[serde(with = "string_int")]
+    pub num_satoshis: u64,
+    #[serde(with = "string_int")]
+    pub timestamp: i64,
+    #[serde(with = "string_int")]
+    pub expiry: i64,
+    pub description: String,
+    /// Hex encoded, empty unless the invoice commits to a description hash.
+    pub description_hash: String,
+    pub fallback_addr: String,
+    #[serde(with = "string_int")]
+    pub cltv_expiry: u64,
+    pub route_hints: Vec<LndRouteHint>,
+    #[serde(with = "base64_bytes")]
+    pub payment_addr: Vec<u8>,
+    #[serde(with = "string_int")]
+    pub num_msat: u64,
+    pub features: HashMap<u32, LndFeature>,
+}
+impl LndPayReq {
+    /// Whether LND's decoding agrees with the local one on what gets paid, to whom,
+    /// for what and until when.
+    pub fn matches(&self, decoded: &DecodedInvoice) -> bool {
+        let (Ok(payment_hash), Ok(description_hash)) =
+            (decoded.payment_hash(), decoded.description_hash())
+        else {
+            return false;
+        };
+        let description_hash = match description_hash {
+            Some(hash) => bytes32(self.description_hash.as_bytes()).is_ok_and(|lnd| lnd == hash),
+            None => self.description_hash.is_empty(),
+        };
+        self.payment_hash == payment_hash
+            && self.num_msat == decoded.amount_msat().unwrap_or_default()
+            && self.destination == decoded.payee_pubkey()
+            && self.payment_addr == decoded.payment_secret()
+            && self.timestamp == decoded.timestamp() as i64
+            && self.expiry == decoded.expiry().as_secs() as i64
+            && self.cltv_expiry == decoded.min_final_cltv_expiry_delta()
+            && self.description == decoded.description().unwrap_or_default()
+            && description_hash
+    }
+}
+impl TryFrom<String> for LndPayReq {
+    type Error = BrightError;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Ok(serde_json::from_str(&value)?)
+    }
+}
+impl TryInto<String> for LndPayReq {
+    type Error = BrightError;
+    fn try_into(self) -> Result<String, Self::Error> {
+        Ok(serde_json::to_string(&self)?)
+    }
+}
+impl Display for LndPayReq {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", serde_json::to_string_pretty(self).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Bolt11PaymentRequest, DecodedInvoice};
+    use crate::{
+        testing::{mock_bolt11, MockLnd},
+        LnAddressPaymentRequest, LndInvoiceRequestBody, PaymentHash,
+    };
+
+    #[test]
+    fn decodes_bolt11_fields() -> anyhow::Result<()> {
+        let hash = PaymentHash::from_bytes([0x42; 32]);
+        let request = LnAddressPaymentRequest {
+            pr: mock_bolt11(&hash, 5_000, "Coffee"),
+        };
+        let decoded = request.decode()?;
+        assert_eq!(decoded.payment_hash()?, hash);
+        assert_eq!(decoded.description_hash()?, None);
+        assert_eq!(decoded.payment_secret(), [0x33; 32]);
+        assert_eq!(decoded.amount_msat(), Some(5_000));
+        assert_eq!(decoded.description().as_deref(), Some("Coffee"));
+        assert_eq!(decoded.expiry().as_secs(), 86400);
+        assert!(!decoded.is_expired());
+        assert!(decoded.is_expired_at(decoded.expires_at()));
+        assert_eq!(decoded.min_final_cltv_expiry_delta(), 80);
+        assert_eq!(decoded.network(), "regtest");
+        assert!(decoded.route_hints().is_empty());
+        assert_eq!(decoded.payment_request(), request.pr);
+
+        let json = serde_json::to_string(&decoded)?;
+        assert_eq!(serde_json::from_str::<DecodedInvoice>(&json)?, decoded);
+        assert!("lnbc1".parse::<DecodedInvoice>().is_err());
+        Ok(())
+    }
+    #[tokio::test]
+    async fn lnd_decoding_matches_local() -> anyhow::Result<()> {
+        let lnd = MockLnd::start().await;
+        let client = lnd.client();
+        let created = client
+            .get_invoice(LndInvoiceRequestBody::new(12, Some("Cross".to_string())))
+            .await?;
+        let decoded = created.decode()?;
+        let pay_req = client.decode_pay_req(&created.payment_request).await?;
+        assert!(pay_req.matches(&decoded));
+        let mut other = pay_req.clone();
+        other.description = "Other".to_string();
+        assert!(!other.matches(&decoded));
+        other.description = String::new();
+        other.description_hash = "ab".repeat(32);
+        assert!(!other.matches(&decoded));
+        assert_eq!(pay_req.num_satoshis, 12);
+        assert_eq!(pay_req.description, "Cross");
+        assert_eq!(
+            client
+                .lookup_invoice_by_hash(&created.r_hash)
+                .await?
+                .decode()?,
+            decoded
+        );
+
+        let hodl = client.hodl_invoice().value(3).create().await?;
+        assert_eq!(
+            hodl.invoice().decode()?.payment_hash()?,
+            hodl.payment_hash()
+        );
+        assert!(client.decode_pay_req("lnbc1").await.is_err());
+        Ok(())
+    }
+}
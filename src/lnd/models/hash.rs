@@ -0,0 +1,256 @@
+use std::{fmt::Display, str::FromStr};
+
+use base64::prelude::*;
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest, Sha256};
+
+use super::base64_bytes;
+use crate::{BrightError, BrightResult};
+
+/// Reads a 32 byte value given as raw bytes, hex, or standard or URL safe base64.
+pub(crate) fn bytes32(value: &[u8]) -> BrightResult<[u8; 32]> {
+    if let Ok(bytes) = <[u8; 32]>::try_from(value) {
+        return Ok(bytes);
+    }
+    let text = std::str::from_utf8(value)
+        .map_err(|_| BrightError::invalid_input("Expected 32 bytes, hex or base64"))?;
+    text_bytes32(text)
+}
+
+/// Reads a 32 byte value given as hex, or standard or URL safe base64.
+fn text_bytes32(text: &str) -> BrightResult<[u8; 32]> {
+    let text = text.trim();
+    let decoded = match text.len() {
+        64 if !text.bytes().all(|b| b.is_ascii_hexdigit()) => {
+            return Err(BrightError::invalid_input(
+                "Invalid hex: expected 0-9 and a-f",
+            ))
+        }
+        64 => (0..64)
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&text[i..i + 2], 16))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| BrightError::invalid_input(format!("Invalid hex: {e}")))?,
+        _ => base64_bytes::decode(text)
+            .map_err(|e| BrightError::invalid_input(format!("Invalid base64: {e}")))?,
+    };
+    <[u8; 32]>::try_from(decoded).map_err(|bytes| {
+        BrightError::invalid_input(format!("Expected 32 bytes, got {}", bytes.len()))
+    })
+}
+
+/// Implements the encodings shared by the 32 byte hash types.
+///
+/// Parsing accepts hex, base64 and base64url. `Display` is hex, serde uses the
+/// base64 LND puts in its JSON but reads any of the encodings.
+macro_rules! bytes32_type {
+    ($name:ident) => {
+        impl $name {
+            pub fn from_bytes(bytes: [u8; 32]) -> Self {
+                Self(bytes)
+            }
+            pub fn as_bytes(&self) -> &[u8; 32] {
+                &self.0
+            }
+            pub fn to_hex(&self) -> String {
+                self.0.iter().map(|b| format!("{:02x}", b)).collect()
+            }
+            pub fn to_base64(&self) -> String {
+                BASE64_STANDARD.encode(self.0)
+            }
+            /// Encoding used in the paths of LND's REST and websocket endpoints.
+            pub fn to_base64_url(&self) -> String {
+                BASE64_URL_SAFE.encode(self.0)
+            }
+            pub fn from_hex(value: &str) -> BrightResult<Self> {
+                match value.len() {
+                    64 => value.parse(),
+                    _ => Err(BrightError::invalid_input("Expected 64 hex characters")),
+                }
+            }
+            pub fn from_base64(value: &str) -> BrightResult<Self> {
+                let bytes = base64_bytes::decode(value)
+                    .map_err(|e| BrightError::invalid_input(format!("Invalid base64: {e}")))?;
+                Self::try_from(bytes.as_slice())
+            }
+        }
+        impl From<[u8; 32]> for $name {
+            fn from(value: [u8; 32]) -> Self {
+                Self(value)
+            }
+        }
+        impl From<$name> for [u8; 32] {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+        impl TryFrom<&[u8]> for $name {
+            type Error = BrightError;
+            fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+                <[u8; 32]>::try_from(value).map(Self).map_err(|_| {
+                    BrightError::invalid_input(format!("Expected 32 bytes, got {}", value.len()))
+                })
+            }
+        }
+        impl TryFrom<String> for $name {
+            type Error = BrightError;
+            fn try_from(value: String) -> Result<Self, Self::Error> {
+                value.parse()
+            }
+        }
+        impl FromStr for $name {
+            type Err = BrightError;
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                Ok(Self(text_bytes32(s)?))
+            }
+        }
+        impl AsRef<[u8]> for $name {
+            fn as_ref(&self) -> &[u8] {
+                &self.0
+            }
+        }
+        impl Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{}", self.to_hex())
+            }
+        }
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(&self.to_base64())
+            }
+        }
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                String::deserialize(deserializer)?
+                    .parse()
+                    .map_err(D::Error::custom)
+            }
+        }
+    };
+}
+
+/// SHA-256 payment hash of an invoice.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PaymentHash([u8; 32]);
+bytes32_type!(PaymentHash);
+impl std::fmt::Debug for PaymentHash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PaymentHash({})", self.to_hex())
+    }
+}
+
+/// Secret released when an invoice is settled, hashing to its [`PaymentHash`].
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Preimage([u8; 32]);
+bytes32_type!(Preimage);
+/// Redacted, so the secret does not end up in logs of the types holding it.
+impl std::fmt::Debug for Preimage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Preimage(..)")
+    }
+}
+impl Preimage {
+    /// Fresh preimage from the operating system's random number generator.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn random() -> BrightResult<Self> {
+        let mut bytes = [0u8; 32];
+        getrandom::getrandom(&mut bytes).map_err(|e| std::io::Error::other(e.to_string()))?;
+        Ok(Self(bytes))
+    }
+    pub fn payment_hash(&self) -> PaymentHash {
+        PaymentHash(Sha256::digest(self.0).into())
+    }
+    /// Whether this preimage settles invoices with the given hash.
+    pub fn matches(&self, payment_hash: &PaymentHash) -> bool {
+        self.payment_hash() == *payment_hash
+    }
+}
+
+/// Serde helpers for optional hashes, which LND sends as empty strings or, for
+/// in-flight payments, as all zeroes.
+pub(crate) mod optional {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub(crate) fn serialize<T, S>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Serialize,
+        S: Serializer,
+    {
+        match value {
+            Some(value) => value.serialize(serializer),
+            None => serializer.serialize_str(""),
+        }
+    }
+
+    pub(crate) fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+    where
+        T: std::str::FromStr + Default + PartialEq,
+        T::Err: std::fmt::Display,
+        D: Deserializer<'de>,
+    {
+        let value = Option::<String>::deserialize(deserializer)?.unwrap_or_default();
+        if value.is_empty() {
+            return Ok(None);
+        }
+        let value = value.parse::<T>().map_err(serde::de::Error::custom)?;
+        Ok((value != T::default()).then_some(value))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PaymentHash, Preimage};
+
+    #[test]
+    fn parses_and_formats_encodings() -> anyhow::Result<()> {
+        let hex = "deadbeef".repeat(8);
+        let hash: PaymentHash = hex.parse()?;
+        assert_eq!(hash.to_hex(), hex);
+        assert_eq!(hash.to_string(), hex);
+        assert_eq!(PaymentHash::from_base64(&hash.to_base64())?, hash);
+        assert_eq!(hash.to_base64_url().parse::<PaymentHash>()?, hash);
+        assert_eq!(PaymentHash::from_hex(&hex)?, hash);
+        assert_eq!(PaymentHash::try_from(&hash.as_bytes()[..])?, hash);
+        assert!(PaymentHash::from_hex(&hash.to_base64()).is_err());
+        assert!("zz".repeat(32).parse::<PaymentHash>().is_err());
+        assert!(PaymentHash::try_from(&[0u8; 31][..]).is_err());
+
+        let json = serde_json::to_string(&hash)?;
+        assert_eq!(json, format!("\"{}\"", hash.to_base64()));
+        assert_eq!(serde_json::from_str::<PaymentHash>(&json)?, hash);
+        assert_eq!(
+            serde_json::from_str::<PaymentHash>(&format!("\"{}\"", hex))?,
+            hash
+        );
+        Ok(())
+    }
+    #[test]
+    fn rejects_multibyte_text_of_hex_length() {
+        let text = format!("a{}", "€".repeat(21));
+        assert_eq!(text.len(), 64);
+        assert!(super::bytes32(text.as_bytes()).is_err());
+        assert!(text.parse::<PaymentHash>().is_err());
+        assert!(serde_json::from_str::<Preimage>(&format!("\"{text}\"")).is_err());
+        assert!(format!("+{}", "a".repeat(63))
+            .parse::<PaymentHash>()
+            .is_err());
+        let short = "a".repeat(32).parse::<PaymentHash>().unwrap_err();
+        assert_eq!(
+            short.to_string(),
+            "Invalid input: Expected 32 bytes, got 24"
+        );
+    }
+    #[test]
+    fn preimage_hashes_to_payment_hash() -> anyhow::Result<()> {
+        let preimage = Preimage::from_bytes([0; 32]);
+        let expected: PaymentHash =
+            "66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925".parse()?;
+        assert_eq!(preimage.payment_hash(), expected);
+        assert!(preimage.matches(&expected));
+        assert!(!Preimage::from_bytes([1; 32]).matches(&expected));
+        let secret = Preimage::from_bytes([0xab; 32]);
+        assert_eq!(format!("{:?}", secret), "Preimage(..)");
+        assert!(!format!("{:?}", Some(secret)).contains("abab"));
+        Ok(())
+    }
+}
@@ -1,43 +1,183 @@
-use base64::prelude::*;
 use lightning_invoice::Bolt11Invoice;
 use serde::{Deserialize, Serialize};
-use std::fmt::Display;
+use std::{fmt::Display, time::Duration};
 
+use super::{
+    base64_bytes, is_default, string_int, Bolt11PaymentRequest, DecodedInvoice, PaymentHash,
+};
+use crate::{BrightError, BrightResult};
+
+/// Body of LND's `AddHoldInvoice` call on `/v2/invoices/hodl`.
+///
+/// Unlike a regular invoice LND does not know the preimage, the invoice stays
+/// `ACCEPTED` once paid until it is settled or canceled.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct LndHodlInvoiceRequestBody {
+    pub(crate) hash: PaymentHash,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) memo: Option<String>,
+    #[serde(with = "string_int", skip_serializing_if = "is_default")]
+    pub(crate) value: u64,
+    #[serde(with = "string_int", skip_serializing_if = "is_default")]
+    pub(crate) value_msat: u64,
+    #[serde(with = "base64_bytes", skip_serializing_if = "Vec::is_empty")]
+    pub(crate) description_hash: Vec<u8>,
+    #[serde(with = "string_int", skip_serializing_if = "is_default")]
+    pub(crate) expiry: u64,
+    #[serde(with = "string_int", skip_serializing_if = "is_default")]
+    pub(crate) cltv_expiry: u64,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) private: bool,
+}
+impl LndHodlInvoiceRequestBody {
+    /// Hodl invoice for `value` satoshis locked to `hash`.
+    pub fn new(hash: PaymentHash, value: u64) -> Self {
+        Self {
+            hash,
+            value,
+            ..Default::default()
+        }
+    }
+    pub fn hash(&self) -> PaymentHash {
+        self.hash
+    }
+    /// Amount of the invoice in millisatoshis, an error if `value` overflows them.
+    pub fn amount_msat(&self) -> BrightResult<u64> {
+        match self.value_msat {
+            0 => self
+                .value
+                .checked_mul(1000)
+                .ok_or_else(|| BrightError::invalid_input("Amount overflows millisatoshis")),
+            msat => Ok(msat),
+        }
+    }
+    pub fn memo(&self) -> Option<&str> {
+        self.memo.as_deref()
+    }
+    /// Checks the options LND would reject.
+    pub fn validate(&self) -> BrightResult<()> {
+        if self.value > 0 && self.value_msat > 0 {
+            return Err(BrightError::invalid_input(
+                "Only one of value and value_msat can be set",
+            ));
+        }
+        self.amount_msat()?;
+        Ok(())
+    }
+}
+impl Display for LndHodlInvoiceRequestBody {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", serde_json::to_string(self).unwrap())
+    }
+}
+
+/// Response of `AddHoldInvoice` as sent by LND.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct LndHodlInvoiceResponse {
+    payment_request: String,
+    #[serde(with = "string_int")]
+    add_index: u64,
+    #[serde(with = "base64_bytes")]
+    payment_addr: Vec<u8>,
+}
+
+/// Hodl invoice returned by LND, with its BOLT11 payment request decoded once
+/// when it is read.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(try_from = "LndHodlInvoiceResponse", into = "LndHodlInvoiceResponse")]
 pub struct LndHodlInvoice {
-    payment_addr: String,
     payment_request: String,
-    add_index: String,
+    add_index: u64,
+    payment_hash: PaymentHash,
+    decoded: DecodedInvoice,
 }
 impl LndHodlInvoice {
-    pub fn payment_hash(&self) -> Vec<u8> {
-        self.payment_addr.as_bytes().to_vec()
+    pub fn payment_hash(&self) -> PaymentHash {
+        self.payment_hash
+    }
+    /// Payment secret of the invoice, LND's `payment_addr`.
+    pub fn payment_addr(&self) -> [u8; 32] {
+        self.decoded.payment_secret()
     }
     pub fn payment_request(&self) -> String {
         self.payment_request.clone()
     }
-    pub fn r_hash_url_safe(&self) -> anyhow::Result<String> {
-        let r_hash = self
-            .payment_request
-            .parse::<Bolt11Invoice>()
-            .map_err(|e| anyhow::anyhow!(e.to_string()))?;
-        let url_safe = BASE64_URL_SAFE.encode(r_hash.payment_hash());
-        Ok(url_safe)
+    pub fn add_index(&self) -> u64 {
+        self.add_index
+    }
+    pub fn bolt11(&self) -> &Bolt11Invoice {
+        self.decoded.bolt11()
+    }
+    pub fn r_hash_url_safe(&self) -> String {
+        self.payment_hash().to_base64_url()
     }
-    pub fn sat_amount(&self) -> u64 {
-        let bolt11 = self.payment_request.clone();
-        let bolt11 = bolt11.parse::<Bolt11Invoice>().unwrap();
-        bolt11.amount_milli_satoshis().unwrap() / 1000
+    /// Amount of the invoice in millisatoshis, `None` for amountless invoices.
+    pub fn amount_msat(&self) -> Option<u64> {
+        self.decoded.amount_msat()
+    }
+    /// Amount of the invoice in whole satoshis, `None` for amountless invoices.
+    pub fn sat_amount(&self) -> Option<u64> {
+        self.amount_msat().map(|msat| msat / 1000)
+    }
+    pub fn expiry(&self) -> Duration {
+        self.decoded.expiry()
+    }
+    /// Unix timestamp at which the invoice expires.
+    pub fn expires_at(&self) -> u64 {
+        self.decoded.expires_at()
+    }
+    /// Memo of the invoice, `None` when it commits to a description hash instead.
+    pub fn description(&self) -> Option<String> {
+        self.decoded.description()
+    }
+    pub fn description_hash(&self) -> BrightResult<Option<[u8; 32]>> {
+        self.decoded.description_hash()
+    }
+    /// Hex encoded public key of the node that issued the invoice.
+    pub fn payee_pubkey(&self) -> String {
+        self.decoded.payee_pubkey()
+    }
+}
+impl Bolt11PaymentRequest for LndHodlInvoice {
+    fn payment_request_str(&self) -> &str {
+        &self.payment_request
+    }
+    /// Already decoded when the invoice was read.
+    fn decode(&self) -> BrightResult<DecodedInvoice> {
+        Ok(self.decoded.clone())
+    }
+}
+impl TryFrom<LndHodlInvoiceResponse> for LndHodlInvoice {
+    type Error = BrightError;
+    fn try_from(value: LndHodlInvoiceResponse) -> Result<Self, Self::Error> {
+        let decoded: DecodedInvoice = value.payment_request.parse()?;
+        Ok(Self {
+            payment_hash: decoded.payment_hash()?,
+            decoded,
+            payment_request: value.payment_request,
+            add_index: value.add_index,
+        })
+    }
+}
+impl From<LndHodlInvoice> for LndHodlInvoiceResponse {
+    fn from(value: LndHodlInvoice) -> Self {
+        Self {
+            payment_addr: value.payment_addr().to_vec(),
+            payment_request: value.payment_request,
+            add_index: value.add_index,
+        }
     }
 }
 impl TryFrom<String> for LndHodlInvoice {
-    type Error = anyhow::Error;
+    type Error = BrightError;
     fn try_from(value: String) -> Result<Self, Self::Error> {
         Ok(serde_json::from_str(&value)?)
     }
 }
 impl TryInto<String> for LndHodlInvoice {
-    type Error = anyhow::Error;
+    type Error = BrightError;
     fn try_into(self) -> Result<String, Self::Error> {
         Ok(serde_json::to_string(&self)?)
     }
@@ -56,19 +196,19 @@ pub enum HodlState {
     SETTLED,
 }
 impl TryFrom<String> for HodlState {
-    type Error = anyhow::Error;
+    type Error = BrightError;
     fn try_from(value: String) -> Result<Self, Self::Error> {
         match value.as_str() {
             "OPEN" => Ok(HodlState::OPEN),
             "ACCEPTED" => Ok(HodlState::ACCEPTED),
             "CANCELED" => Ok(HodlState::CANCELED),
             "SETTLED" => Ok(HodlState::SETTLED),
-            _ => Err(anyhow::anyhow!("Invalid HodlState")),
+            _ => Err(BrightError::invalid_input("Invalid HodlState")),
         }
     }
 }
 impl TryInto<String> for HodlState {
-    type Error = anyhow::Error;
+    type Error = BrightError;
     fn try_into(self) -> Result<String, Self::Error> {
         match self {
             HodlState::OPEN => Ok("OPEN".to_string()),
@@ -88,17 +228,17 @@ impl Display for HodlState {
 pub struct LndHodlInvoiceState {
     settled: bool,
     state: HodlState,
-    r_hash: String,
+    r_hash: PaymentHash,
     payment_request: String,
 }
 impl TryFrom<String> for LndHodlInvoiceState {
-    type Error = anyhow::Error;
+    type Error = BrightError;
     fn try_from(value: String) -> Result<Self, Self::Error> {
         Ok(serde_json::from_str(&value)?)
     }
 }
 impl TryInto<String> for LndHodlInvoiceState {
-    type Error = anyhow::Error;
+    type Error = BrightError;
     fn try_into(self) -> Result<String, Self::Error> {
         Ok(serde_json::to_string(&self)?)
     }
@@ -115,14 +255,59 @@ impl LndHodlInvoiceState {
     pub fn state(&self) -> HodlState {
         self.state.clone()
     }
-    pub fn r_hash(&self) -> String {
-        self.r_hash.clone()
+    pub fn r_hash(&self) -> PaymentHash {
+        self.r_hash
     }
     pub fn r_hash_url_safe(&self) -> String {
-        let url_safe = BASE64_URL_SAFE.encode(self.r_hash.as_bytes());
-        url_safe
+        self.r_hash.to_base64_url()
     }
     pub fn payment_request(&self) -> String {
         self.payment_request.clone()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::LndHodlInvoice;
+    use crate::{testing::mock_bolt11, PaymentHash};
+
+    #[test]
+    fn decodes_payment_request_once() -> anyhow::Result<()> {
+        let hash = PaymentHash::from_bytes([0xab; 32]);
+        let json = serde_json::json!({
+            "payment_request": mock_bolt11(&hash, 21_500, "Hodl"),
+            "add_index": "8",
+            "payment_addr": "MzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzM=",
+        });
+        let invoice = LndHodlInvoice::try_from(json.to_string())?;
+        assert_eq!(invoice.payment_hash(), hash);
+        assert_eq!(invoice.payment_addr(), [0x33; 32]);
+        assert_eq!(invoice.add_index(), 8);
+        assert_eq!(invoice.amount_msat(), Some(21_500));
+        assert_eq!(invoice.sat_amount(), Some(21));
+        assert_eq!(invoice.description().as_deref(), Some("Hodl"));
+        assert_eq!(invoice.description_hash()?, None);
+        assert_eq!(invoice.expiry().as_secs(), 86400);
+        assert!(invoice.expires_at() > 86400);
+        assert_eq!(invoice.payee_pubkey().len(), 66);
+
+        let round_trip: serde_json::Value = serde_json::to_value(&invoice)?;
+        assert_eq!(round_trip, json);
+        Ok(())
+    }
+    #[test]
+    fn handles_amountless_and_invalid_requests() -> anyhow::Result<()> {
+        let hash = PaymentHash::from_bytes([0xcd; 32]);
+        let json = serde_json::json!({
+            "payment_request": mock_bolt11(&hash, 0, ""),
+            "add_index": "1",
+        });
+        let invoice = LndHodlInvoice::try_from(json.to_string())?;
+        assert_eq!(invoice.amount_msat(), None);
+        assert_eq!(invoice.sat_amount(), None);
+
+        let invalid = r#"{"payment_request":"lnbc1","add_index":"1"}"#;
+        assert!(LndHodlInvoice::try_from(invalid.to_string()).is_err());
+        Ok(())
+    }
+}
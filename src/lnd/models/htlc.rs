@@ -0,0 +1,232 @@
+use std::{collections::HashMap, fmt::Display};
+
+use serde::{Deserialize, Serialize};
+
+use super::{base64_bytes, optional, string_int, Preimage};
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum LndHtlcStatus {
+    #[default]
+    #[serde(rename = "IN_FLIGHT")]
+    InFlight,
+    #[serde(rename = "SUCCEEDED")]
+    Succeeded,
+    #[serde(rename = "FAILED")]
+    Failed,
+}
+
+/// One attempt of the router to deliver (part of) a payment.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct LndHtlcAttempt {
+    #[serde(with = "string_int")]
+    pub attempt_id: u64,
+    pub status: LndHtlcStatus,
+    pub route: LndRoute,
+    #[serde(with = "string_int")]
+    pub attempt_time_ns: i64,
+    #[serde(with = "string_int")]
+    pub resolve_time_ns: i64,
+    /// Why the attempt failed, set once it has.
+    pub failure: Option<LndHtlcFailure>,
+    #[serde(with = "optional")]
+    pub preimage: Option<Preimage>,
+}
+impl LndHtlcAttempt {
+    /// Hop that reported the failure, if it is part of the route.
+    pub fn failure_source(&self) -> Option<&LndHop> {
+        let index = self.failure.as_ref()?.failure_source_index as usize;
+        // Index zero is this node, hops start at one.
+        self.route.hops.get(index.checked_sub(1)?)
+    }
+}
+
+/// Route an HTLC took, as in LND's `Route` message.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct LndRoute {
+    pub total_time_lock: u32,
+    pub hops: Vec<LndHop>,
+    #[serde(with = "string_int")]
+    pub total_fees_msat: u64,
+    #[serde(with = "string_int")]
+    pub total_amt_msat: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct LndHop {
+    #[serde(with = "string_int")]
+    pub chan_id: u64,
+    pub expiry: u32,
+    #[serde(with = "string_int")]
+    pub amt_to_forward_msat: u64,
+    #[serde(with = "string_int")]
+    pub fee_msat: u64,
+    /// Hex encoded public key of the node this hop leads to.
+    pub pub_key: String,
+    pub tlv_payload: bool,
+    pub mpp_record: Option<LndMppRecord>,
+    pub amp_record: Option<LndAmpRecord>,
+    #[serde(with = "base64_bytes::map")]
+    pub custom_records: HashMap<u64, Vec<u8>>,
+    #[serde(with = "base64_bytes")]
+    pub metadata: Vec<u8>,
+}
+
+/// Multi-path payment record sent to the final hop.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct LndMppRecord {
+    #[serde(with = "base64_bytes")]
+    pub payment_addr: Vec<u8>,
+    #[serde(with = "string_int")]
+    pub total_amt_msat: u64,
+}
+
+/// Atomic multi-path payment record sent to the final hop.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct LndAmpRecord {
+    #[serde(with = "base64_bytes")]
+    pub root_share: Vec<u8>,
+    #[serde(with = "base64_bytes")]
+    pub set_id: Vec<u8>,
+    pub child_index: u32,
+}
+
+/// Failure an HTLC attempt came back with.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct LndHtlcFailure {
+    pub code: LndFailureCode,
+    #[serde(with = "string_int")]
+    pub htlc_msat: u64,
+    pub cltv_expiry: u32,
+    pub flags: u32,
+    /// Position in the route of the node that failed the HTLC, zero for this node.
+    pub failure_source_index: u32,
+    pub height: u32,
+}
+
+/// BOLT4 failure code, as named in LND's `Failure` message.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum LndFailureCode {
+    #[default]
+    Reserved,
+    IncorrectOrUnknownPaymentDetails,
+    IncorrectPaymentAmount,
+    FinalIncorrectCltvExpiry,
+    FinalIncorrectHtlcAmount,
+    FinalExpiryTooSoon,
+    InvalidRealm,
+    ExpiryTooSoon,
+    InvalidOnionVersion,
+    InvalidOnionHmac,
+    InvalidOnionKey,
+    AmountBelowMinimum,
+    FeeInsufficient,
+    IncorrectCltvExpiry,
+    ChannelDisabled,
+    TemporaryChannelFailure,
+    RequiredNodeFeatureMissing,
+    RequiredChannelFeatureMissing,
+    UnknownNextPeer,
+    TemporaryNodeFailure,
+    PermanentNodeFailure,
+    PermanentChannelFailure,
+    ExpiryTooFar,
+    MppTimeout,
+    InvalidOnionPayload,
+    InvalidOnionBlinding,
+    InternalFailure,
+    UnknownFailure,
+    UnreadableFailure,
+    /// Code added to LND after this crate.
+    #[serde(other)]
+    Unknown,
+}
+impl Display for LndFailureCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            serde_json::to_string(self).unwrap().trim_matches('"')
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{LndFailureCode, LndHtlcAttempt, LndHtlcStatus};
+
+    #[test]
+    fn deserializes_failed_attempt() -> anyhow::Result<()> {
+        let attempt: LndHtlcAttempt = serde_json::from_value(serde_json::json!({
+            "attempt_id": "7",
+            "status": "FAILED",
+            "route": {
+                "total_time_lock": 820,
+                "total_fees": "1",
+                "total_amt": "101",
+                "total_fees_msat": "1000",
+                "total_amt_msat": "101000",
+                "hops": [
+                    {
+                        "chan_id": "123456789",
+                        "chan_capacity": "1000000",
+                        "expiry": 820,
+                        "amt_to_forward_msat": "100000",
+                        "fee_msat": "1000",
+                        "pub_key": "02aa",
+                        "tlv_payload": true,
+                        "mpp_record": null,
+                        "custom_records": null
+                    },
+                    {
+                        "chan_id": "987654321",
+                        "expiry": 800,
+                        "amt_to_forward_msat": "100000",
+                        "pub_key": "03bb",
+                        "tlv_payload": true,
+                        "mpp_record": { "payment_addr": "MzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzM=", "total_amt_msat": "100000" },
+                        "custom_records": { "5482373484": "AQI=" }
+                    }
+                ]
+            },
+            "attempt_time_ns": "1700000000000000000",
+            "resolve_time_ns": "1700000001000000000",
+            "failure": {
+                "code": "TEMPORARY_CHANNEL_FAILURE",
+                "channel_update": null,
+                "htlc_msat": "0",
+                "onion_sha_256": "",
+                "cltv_expiry": 0,
+                "flags": 0,
+                "failure_source_index": 1,
+                "height": 0
+            },
+            "preimage": ""
+        }))?;
+        assert_eq!(attempt.status, LndHtlcStatus::Failed);
+        assert_eq!(attempt.route.total_fees_msat, 1_000);
+        assert_eq!(attempt.route.hops.len(), 2);
+        assert_eq!(attempt.route.hops[0].chan_id, 123_456_789);
+        let mpp = attempt.route.hops[1].mpp_record.as_ref().unwrap();
+        assert_eq!(mpp.payment_addr, vec![0x33; 32]);
+        assert_eq!(
+            attempt.route.hops[1].custom_records[&5_482_373_484],
+            vec![1, 2]
+        );
+        assert!(attempt.preimage.is_none());
+        let failure = attempt.failure.as_ref().unwrap();
+        assert_eq!(failure.code, LndFailureCode::TemporaryChannelFailure);
+        assert_eq!(failure.code.to_string(), "TEMPORARY_CHANNEL_FAILURE");
+        assert_eq!(attempt.failure_source().unwrap().pub_key, "02aa");
+
+        let code: LndFailureCode = serde_json::from_str("\"SOMETHING_NEW\"")?;
+        assert_eq!(code, LndFailureCode::Unknown);
+        Ok(())
+    }
+}
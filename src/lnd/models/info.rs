@@ -1,25 +1,122 @@
 use serde::{Deserialize, Serialize};
-use std::fmt::Display;
+use std::{collections::HashMap, fmt::Display};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use super::string_int;
+use crate::BrightError;
+
+/// Feature bit advertised by a node or invoice, keyed by its bit number.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(default)]
+pub struct LndFeature {
+    pub name: String,
+    pub is_required: bool,
+    pub is_known: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct LndChain {
+    pub chain: String,
+    pub network: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct LndInfo {
+    version: String,
+    commit_hash: String,
     identity_pubkey: String,
+    alias: String,
+    color: String,
+    num_pending_channels: u32,
+    num_active_channels: u32,
+    num_inactive_channels: u32,
+    num_peers: u32,
     block_height: u32,
+    block_hash: String,
+    #[serde(with = "string_int")]
+    best_header_timestamp: i64,
+    synced_to_chain: bool,
+    synced_to_graph: bool,
+    chains: Vec<LndChain>,
+    uris: Vec<String>,
+    features: HashMap<u32, LndFeature>,
+}
+impl LndInfo {
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+    pub fn commit_hash(&self) -> &str {
+        &self.commit_hash
+    }
+    pub fn identity_pubkey(&self) -> &str {
+        &self.identity_pubkey
+    }
+    pub fn alias(&self) -> &str {
+        &self.alias
+    }
+    pub fn color(&self) -> &str {
+        &self.color
+    }
+    pub fn num_pending_channels(&self) -> u32 {
+        self.num_pending_channels
+    }
+    pub fn num_active_channels(&self) -> u32 {
+        self.num_active_channels
+    }
+    pub fn num_inactive_channels(&self) -> u32 {
+        self.num_inactive_channels
+    }
+    pub fn num_peers(&self) -> u32 {
+        self.num_peers
+    }
+    pub fn block_height(&self) -> u32 {
+        self.block_height
+    }
+    pub fn block_hash(&self) -> &str {
+        &self.block_hash
+    }
+    /// Unix timestamp of the best block header known to the node.
+    pub fn best_header_timestamp(&self) -> i64 {
+        self.best_header_timestamp
+    }
+    pub fn synced_to_chain(&self) -> bool {
+        self.synced_to_chain
+    }
+    pub fn synced_to_graph(&self) -> bool {
+        self.synced_to_graph
+    }
+    /// Whether the node is synced to both the chain and the channel graph.
+    pub fn is_synced(&self) -> bool {
+        self.synced_to_chain && self.synced_to_graph
+    }
+    pub fn chains(&self) -> &[LndChain] {
+        &self.chains
+    }
+    /// Network of the node's first chain, e.g. `mainnet`, `testnet` or `regtest`.
+    pub fn network(&self) -> Option<&str> {
+        self.chains.first().map(|chain| chain.network.as_str())
+    }
+    pub fn uris(&self) -> &[String] {
+        &self.uris
+    }
+    pub fn features(&self) -> &HashMap<u32, LndFeature> {
+        &self.features
+    }
 }
 impl TryFrom<&String> for LndInfo {
-    type Error = anyhow::Error;
+    type Error = BrightError;
     fn try_from(value: &String) -> Result<Self, Self::Error> {
         Ok(serde_json::from_str(value)?)
     }
 }
 impl TryFrom<String> for LndInfo {
-    type Error = anyhow::Error;
+    type Error = BrightError;
     fn try_from(value: String) -> Result<Self, Self::Error> {
         Ok(serde_json::from_str(&value)?)
     }
 }
 impl TryInto<String> for LndInfo {
-    type Error = anyhow::Error;
+    type Error = BrightError;
     fn try_into(self) -> Result<String, Self::Error> {
         Ok(serde_json::to_string(&self)?)
     }
@@ -29,3 +126,23 @@ impl Display for LndInfo {
         write!(f, "{}", serde_json::to_string_pretty(self).unwrap())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::testing::MockLnd;
+
+    #[tokio::test]
+    async fn decodes_get_info() -> anyhow::Result<()> {
+        let lnd = MockLnd::start().await;
+        let info = lnd.client().get_info().await?;
+        assert_eq!(info.alias(), "bright-mock");
+        assert_eq!(info.block_height(), lnd.block_height());
+        assert_eq!(info.num_active_channels(), 1);
+        assert!(info.is_synced());
+        assert_eq!(info.network(), Some("regtest"));
+        assert!(info.uris()[0].starts_with(info.identity_pubkey()));
+        assert!(info.features()[&9].is_known);
+        assert!(info.best_header_timestamp() > 0);
+        Ok(())
+    }
+}
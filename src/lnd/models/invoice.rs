@@ -1,11 +1,15 @@
-use base64::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::fmt::Display;
+use std::{collections::HashMap, fmt::Display};
 
-use super::OnchainAddressType;
+use super::{
+    base64_bytes, is_default, optional, string_int, LndFeature, OnchainAddressType, PaymentHash,
+    Preimage,
+};
+use crate::BrightError;
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum LndInvoiceState {
+    #[default]
     #[serde(rename = "OPEN")]
     Open,
     #[serde(rename = "SETTLED")]
@@ -18,19 +22,19 @@ pub enum LndInvoiceState {
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct LndPaymentInvoice {
-    pub r_hash: String,
+    pub r_hash: PaymentHash,
     pub payment_request: String,
     pub add_index: String,
     pub payment_addr: String,
 }
 impl TryFrom<String> for LndPaymentInvoice {
-    type Error = anyhow::Error;
+    type Error = BrightError;
     fn try_from(value: String) -> Result<Self, Self::Error> {
         Ok(serde_json::from_str(&value)?)
     }
 }
 impl TryInto<String> for LndPaymentInvoice {
-    type Error = anyhow::Error;
+    type Error = BrightError;
     fn try_into(self) -> Result<String, Self::Error> {
         Ok(serde_json::to_string(&self)?)
     }
@@ -42,47 +46,149 @@ impl Display for LndPaymentInvoice {
 }
 impl LndPaymentInvoice {
     pub fn r_hash_url_safe(&self) -> String {
-        let unsafe_str = BASE64_STANDARD.decode(&self.r_hash).unwrap();
-        let url_safe = BASE64_URL_SAFE.encode(unsafe_str);
-        url_safe
+        self.r_hash.to_base64_url()
     }
     pub fn r_hash_hex(&self) -> String {
-        let unsafe_str = BASE64_STANDARD.decode(&self.r_hash).unwrap();
-        let hex = unsafe_str
-            .iter()
-            .map(|b| format!("{:02x}", b))
-            .collect::<String>();
-        hex
+        self.r_hash.to_hex()
     }
-    pub fn payment_hash(&self) -> Vec<u8> {
-        BASE64_STANDARD.decode(&self.payment_addr).unwrap()
+    pub fn payment_hash(&self) -> PaymentHash {
+        self.r_hash
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum LndInvoiceHtlcState {
+    #[default]
+    #[serde(rename = "ACCEPTED")]
+    Accepted,
+    #[serde(rename = "SETTLED")]
+    Settled,
+    #[serde(rename = "CANCELED")]
+    Canceled,
+}
+
+/// AMP fields of an HTLC paying an AMP invoice.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct LndAmp {
+    #[serde(with = "base64_bytes")]
+    pub root_share: Vec<u8>,
+    #[serde(with = "base64_bytes")]
+    pub set_id: Vec<u8>,
+    pub child_index: u32,
+    pub hash: PaymentHash,
+    #[serde(with = "optional")]
+    pub preimage: Option<Preimage>,
+}
+
+/// HTLC paying an invoice.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct LndInvoiceHtlc {
+    #[serde(with = "string_int")]
+    pub chan_id: u64,
+    #[serde(with = "string_int")]
+    pub htlc_index: u64,
+    #[serde(with = "string_int")]
+    pub amt_msat: u64,
+    pub accept_height: i32,
+    #[serde(with = "string_int")]
+    pub accept_time: i64,
+    #[serde(with = "string_int")]
+    pub resolve_time: i64,
+    /// Block height at which the HTLC times out and must be resolved.
+    pub expiry_height: i32,
+    pub state: LndInvoiceHtlcState,
+    #[serde(with = "base64_bytes::map")]
+    pub custom_records: HashMap<u64, Vec<u8>>,
+    #[serde(with = "string_int")]
+    pub mpp_total_amt_msat: u64,
+    pub amp: Option<LndAmp>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(default)]
+pub struct LndHopHint {
+    pub node_id: String,
+    #[serde(with = "string_int")]
+    pub chan_id: u64,
+    pub fee_base_msat: u32,
+    pub fee_proportional_millionths: u32,
+    pub cltv_expiry_delta: u32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(default)]
+pub struct LndRouteHint {
+    pub hop_hints: Vec<LndHopHint>,
+}
+
+/// State of one payment set of an AMP invoice.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(default)]
+pub struct LndAmpInvoiceState {
+    pub state: LndInvoiceHtlcState,
+    #[serde(with = "string_int")]
+    pub settle_index: u64,
+    #[serde(with = "string_int")]
+    pub settle_time: i64,
+    #[serde(with = "string_int")]
+    pub amt_paid_msat: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
 pub struct LndInvoice {
-    pub r_preimage: String,
-    pub r_hash: String,
-    pub payment_request: String,
-    pub add_index: String,
-    pub payment_addr: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub memo: Option<String>,
-    pub value: String,
-    pub value_msat: String,
+    #[serde(with = "optional")]
+    pub r_preimage: Option<Preimage>,
+    pub r_hash: PaymentHash,
+    #[serde(with = "string_int")]
+    pub value: u64,
+    #[serde(with = "string_int")]
+    pub value_msat: u64,
     pub settled: bool,
-    pub creation_date: String,
-    pub settle_date: String,
+    #[serde(with = "string_int")]
+    pub creation_date: i64,
+    #[serde(with = "string_int")]
+    pub settle_date: i64,
+    pub payment_request: String,
+    #[serde(with = "base64_bytes")]
+    pub description_hash: Vec<u8>,
+    /// Seconds after `creation_date` at which the invoice expires.
+    #[serde(with = "string_int")]
+    pub expiry: u64,
+    pub fallback_addr: String,
+    #[serde(with = "string_int")]
+    pub cltv_expiry: u64,
+    pub route_hints: Vec<LndRouteHint>,
+    pub private: bool,
+    #[serde(with = "string_int")]
+    pub add_index: u64,
+    #[serde(with = "string_int")]
+    pub settle_index: u64,
+    #[serde(with = "string_int")]
+    pub amt_paid_sat: u64,
+    #[serde(with = "string_int")]
+    pub amt_paid_msat: u64,
     pub state: LndInvoiceState,
+    pub htlcs: Vec<LndInvoiceHtlc>,
+    pub features: HashMap<u32, LndFeature>,
+    pub is_keysend: bool,
+    pub payment_addr: String,
+    pub is_amp: bool,
+    /// Payment sets of an AMP invoice, keyed by hex encoded set id.
+    pub amp_invoice_state: HashMap<String, LndAmpInvoiceState>,
 }
 impl TryFrom<String> for LndInvoice {
-    type Error = anyhow::Error;
+    type Error = BrightError;
     fn try_from(value: String) -> Result<Self, Self::Error> {
         Ok(serde_json::from_str(&value)?)
     }
 }
 impl TryInto<String> for LndInvoice {
-    type Error = anyhow::Error;
+    type Error = BrightError;
     fn try_into(self) -> Result<String, Self::Error> {
         Ok(serde_json::to_string(&self)?)
     }
@@ -93,54 +199,124 @@ impl Display for LndInvoice {
     }
 }
 impl LndInvoice {
+    /// Lowest block height at which an accepted HTLC of the invoice times out.
+    pub fn accepted_expiry_height(&self) -> Option<u32> {
+        self.htlcs
+            .iter()
+            .filter(|htlc| htlc.state == LndInvoiceHtlcState::Accepted)
+            .map(|htlc| htlc.expiry_height.max(0) as u32)
+            .min()
+    }
+    /// Unix timestamp at which the invoice expires.
+    pub fn expires_at(&self) -> i64 {
+        self.creation_date + self.expiry as i64
+    }
     pub fn r_hash_url_safe(&self) -> String {
-        let unsafe_str = BASE64_STANDARD.decode(&self.r_hash).unwrap();
-        let url_safe = BASE64_URL_SAFE.encode(unsafe_str);
-        url_safe
+        self.r_hash.to_base64_url()
     }
     pub fn r_hash_hex(&self) -> String {
-        let unsafe_str = BASE64_STANDARD.decode(&self.r_hash).unwrap();
-        let hex = unsafe_str
-            .iter()
-            .map(|b| format!("{:02x}", b))
-            .collect::<String>();
-        hex
+        self.r_hash.to_hex()
     }
-    pub fn payment_hash(&self) -> Vec<u8> {
-        BASE64_STANDARD.decode(&self.payment_addr).unwrap()
+    pub fn payment_hash(&self) -> PaymentHash {
+        self.r_hash
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct LndInvoiceList {
     pub invoices: Vec<LndInvoice>,
-    pub last_index_offset: String,
-    pub first_index_offset: String,
+    #[serde(with = "string_int")]
+    pub last_index_offset: u64,
+    #[serde(with = "string_int")]
+    pub first_index_offset: u64,
 }
 impl TryFrom<String> for LndInvoiceList {
-    type Error = anyhow::Error;
+    type Error = BrightError;
     fn try_from(value: String) -> Result<Self, Self::Error> {
         Ok(serde_json::from_str(&value)?)
     }
 }
-impl Into<String> for LndInvoiceList {
-    fn into(self) -> String {
-        serde_json::to_string(&self).unwrap()
+impl From<LndInvoiceList> for String {
+    fn from(value: LndInvoiceList) -> Self {
+        serde_json::to_string(&value).unwrap()
     }
 }
+
+/// Filters and paging for `/v1/invoices`.
+///
+/// Invoices are paged by their `add_index`. A forward query returns the invoices after
+/// `index_offset`, a reversed one the invoices before it, starting from the newest when
+/// the offset is zero.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(default)]
+pub struct ListInvoicesQuery {
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pending_only: bool,
+    #[serde(skip_serializing_if = "is_default")]
+    index_offset: u64,
+    #[serde(skip_serializing_if = "is_default")]
+    num_max_invoices: u64,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    reversed: bool,
+    #[serde(skip_serializing_if = "is_default")]
+    creation_date_start: u64,
+    #[serde(skip_serializing_if = "is_default")]
+    creation_date_end: u64,
+}
+impl ListInvoicesQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Only return invoices that are open or accepted.
+    pub fn pending_only(mut self, pending_only: bool) -> Self {
+        self.pending_only = pending_only;
+        self
+    }
+    pub fn index_offset(mut self, index_offset: u64) -> Self {
+        self.index_offset = index_offset;
+        self
+    }
+    /// Page size, LND defaults to 100.
+    pub fn num_max_invoices(mut self, num_max_invoices: u64) -> Self {
+        self.num_max_invoices = num_max_invoices;
+        self
+    }
+    pub fn reversed(mut self, reversed: bool) -> Self {
+        self.reversed = reversed;
+        self
+    }
+    /// Unix timestamp, inclusive.
+    pub fn creation_date_start(mut self, timestamp: u64) -> Self {
+        self.creation_date_start = timestamp;
+        self
+    }
+    /// Unix timestamp, inclusive.
+    pub fn creation_date_end(mut self, timestamp: u64) -> Self {
+        self.creation_date_end = timestamp;
+        self
+    }
+    /// Query for the page following `page`, in the same direction.
+    pub fn next_page(self, page: &LndInvoiceList) -> Self {
+        match self.reversed {
+            true => self.index_offset(page.first_index_offset),
+            false => self.index_offset(page.last_index_offset),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct LndNewAddress {
     pub addr: String,
 }
 impl TryFrom<String> for LndNewAddress {
-    type Error = anyhow::Error;
+    type Error = BrightError;
     fn try_from(value: String) -> Result<Self, Self::Error> {
         Ok(serde_json::from_str(&value)?)
     }
 }
-impl Into<String> for LndNewAddress {
-    fn into(self) -> String {
-        serde_json::to_string(&self).unwrap()
+impl From<LndNewAddress> for String {
+    fn from(value: LndNewAddress) -> Self {
+        serde_json::to_string(&value).unwrap()
     }
 }
 
@@ -152,14 +328,14 @@ pub struct LndNextAddressRequest {
     change: bool,
 }
 impl TryFrom<String> for LndNextAddressRequest {
-    type Error = anyhow::Error;
+    type Error = BrightError;
     fn try_from(value: String) -> Result<Self, Self::Error> {
         Ok(serde_json::from_str(&value)?)
     }
 }
-impl Into<String> for LndNextAddressRequest {
-    fn into(self) -> String {
-        serde_json::to_string(&self).unwrap()
+impl From<LndNextAddressRequest> for String {
+    fn from(value: LndNextAddressRequest) -> Self {
+        serde_json::to_string(&value).unwrap()
     }
 }
 impl Default for LndNextAddressRequest {
@@ -171,3 +347,49 @@ impl Default for LndNextAddressRequest {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{LndInvoice, LndInvoiceHtlcState, LndInvoiceState};
+
+    #[test]
+    fn decodes_full_invoice() -> anyhow::Result<()> {
+        let invoice = LndInvoice::try_from(
+            r#"{"memo":"Zap","r_preimage":"","r_hash":"3q2+7wAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=",
+                "value":"21","value_msat":"21000","settled":true,
+                "creation_date":"1700000000","settle_date":"1700000100",
+                "payment_request":"lnbcrt210n1","description_hash":"q6urq6urq6urq6urq6urq6urq6urq6urq6urq6urq6s=",
+                "expiry":"3600","fallback_addr":"","cltv_expiry":"80",
+                "route_hints":[{"hop_hints":[{"node_id":"02aa","chan_id":"124244814004224",
+                    "fee_base_msat":1000,"fee_proportional_millionths":1,"cltv_expiry_delta":40}]}],
+                "private":true,"add_index":"7","settle_index":"3","amt_paid":"21000",
+                "amt_paid_sat":"21","amt_paid_msat":"21000","state":"SETTLED",
+                "htlcs":[{"chan_id":"124244814004224","htlc_index":"0","amt_msat":"21000",
+                    "accept_height":800000,"accept_time":"1700000050","resolve_time":"1700000100",
+                    "expiry_height":800080,"state":"SETTLED","custom_records":{"5482373484":"AQI="},
+                    "mpp_total_amt_msat":"21000","amp":null}],
+                "features":{"9":{"name":"tlv-onion","is_required":false,"is_known":true}},
+                "is_keysend":false,"payment_addr":"AAAA","is_amp":false,
+                "amp_invoice_state":{}}"#
+                .to_string(),
+        )?;
+        assert_eq!(invoice.state, LndInvoiceState::Settled);
+        assert_eq!(invoice.value_msat, 21_000);
+        assert_eq!(invoice.amt_paid_msat, 21_000);
+        assert_eq!(invoice.add_index, 7);
+        assert_eq!(invoice.expires_at(), 1_700_003_600);
+        assert_eq!(invoice.description_hash, vec![0xab; 32]);
+        assert_eq!(invoice.route_hints[0].hop_hints[0].chan_id, 124244814004224);
+        assert!(invoice.features[&9].is_known);
+
+        let htlc = &invoice.htlcs[0];
+        assert_eq!(htlc.state, LndInvoiceHtlcState::Settled);
+        assert_eq!(htlc.expiry_height, 800_080);
+        assert_eq!(htlc.custom_records[&5482373484], vec![1, 2]);
+        assert!(htlc.amp.is_none());
+
+        let json: String = invoice.clone().try_into()?;
+        assert_eq!(LndInvoice::try_from(json)?, invoice);
+        Ok(())
+    }
+}
@@ -1,4 +1,8 @@
 use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+use super::{base64_bytes, is_default, string_int, Preimage};
+use crate::{BrightError, BrightResult};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LndInvoiceRequest {
@@ -11,41 +15,212 @@ impl LndInvoiceRequest {
         }
     }
     pub fn new(amount: u64) -> Self {
-        let body = LndInvoiceRequestBody {
-            value: amount.to_string(),
-            memo: None,
-        };
-        Self {
-            form: body.to_string(),
-        }
+        Self::from_body(LndInvoiceRequestBody::new(amount, None))
     }
 }
-impl ToString for LndInvoiceRequest {
-    fn to_string(&self) -> String {
-        serde_json::to_string(self).unwrap()
+impl Display for LndInvoiceRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", serde_json::to_string(self).unwrap())
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Body of LND's `AddInvoice` call on `/v1/invoices`.
+///
+/// Built with [`LndInvoiceRequestBody::builder`]. Amounts are serialized as the
+/// string encoded int64s LND expects and unset fields are left out.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
 pub struct LndInvoiceRequestBody {
-    pub value: String,
-    pub memo: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    memo: Option<String>,
+    #[serde(
+        with = "base64_bytes",
+        skip_serializing_if = "Vec::is_empty",
+        rename = "r_preimage"
+    )]
+    preimage: Vec<u8>,
+    #[serde(with = "string_int", skip_serializing_if = "is_default")]
+    value: u64,
+    #[serde(with = "string_int", skip_serializing_if = "is_default")]
+    value_msat: u64,
+    #[serde(with = "base64_bytes", skip_serializing_if = "Vec::is_empty")]
+    description_hash: Vec<u8>,
+    #[serde(with = "string_int", skip_serializing_if = "is_default")]
+    expiry: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fallback_addr: Option<String>,
+    #[serde(with = "string_int", skip_serializing_if = "is_default")]
+    cltv_expiry: u64,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    private: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    is_amp: bool,
 }
-impl Default for LndInvoiceRequestBody {
-    fn default() -> Self {
+impl LndInvoiceRequestBody {
+    /// Invoice for `value` satoshis, zero for an amountless invoice.
+    pub fn new(value: u64, memo: Option<String>) -> Self {
         Self {
-            value: "".to_string(),
-            memo: None,
+            value,
+            memo,
+            ..Default::default()
+        }
+    }
+    pub fn builder() -> LndInvoiceRequestBuilder {
+        LndInvoiceRequestBuilder::default()
+    }
+    /// Amount of the invoice in millisatoshis, an error if `value` overflows them.
+    pub fn amount_msat(&self) -> BrightResult<u64> {
+        match self.value_msat {
+            0 => self
+                .value
+                .checked_mul(1000)
+                .ok_or_else(|| BrightError::invalid_input("Amount overflows millisatoshis")),
+            msat => Ok(msat),
         }
     }
+    pub fn memo(&self) -> Option<&str> {
+        self.memo.as_deref()
+    }
+    pub fn r_preimage(&self) -> Option<Preimage> {
+        Preimage::try_from(self.preimage.as_slice()).ok()
+    }
 }
-impl ToString for LndInvoiceRequestBody {
-    fn to_string(&self) -> String {
-        serde_json::to_string(self).unwrap()
+impl Display for LndInvoiceRequestBody {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", serde_json::to_string(self).unwrap())
     }
 }
-impl LndInvoiceRequestBody {
-    pub fn new(value: String, memo: Option<String>) -> Self {
-        Self { value, memo }
+
+#[derive(Debug, Clone, Default)]
+pub struct LndInvoiceRequestBuilder {
+    body: LndInvoiceRequestBody,
+}
+impl LndInvoiceRequestBuilder {
+    pub fn memo(mut self, memo: impl Into<String>) -> Self {
+        self.body.memo = Some(memo.into());
+        self
+    }
+    /// Amount in satoshis. Cannot be combined with [`Self::value_msat`].
+    pub fn value(mut self, sat: u64) -> Self {
+        self.body.value = sat;
+        self
+    }
+    /// Amount in millisatoshis. Cannot be combined with [`Self::value`].
+    pub fn value_msat(mut self, msat: u64) -> Self {
+        self.body.value_msat = msat;
+        self
+    }
+    /// Seconds until the invoice expires, LND defaults to 24 hours.
+    pub fn expiry(mut self, seconds: u64) -> Self {
+        self.body.expiry = seconds;
+        self
+    }
+    /// SHA256 of the description committed to by the invoice, such as the
+    /// metadata of an LNURL-pay request.
+    pub fn description_hash(mut self, hash: [u8; 32]) -> Self {
+        self.body.description_hash = hash.to_vec();
+        self
+    }
+    /// On-chain address to fall back to if the payment cannot be routed.
+    pub fn fallback_addr(mut self, address: impl Into<String>) -> Self {
+        self.body.fallback_addr = Some(address.into());
+        self
+    }
+    /// CLTV delta of the final hop.
+    pub fn cltv_expiry(mut self, blocks: u64) -> Self {
+        self.body.cltv_expiry = blocks;
+        self
+    }
+    /// Include route hints for private channels.
+    pub fn private(mut self, private: bool) -> Self {
+        self.body.private = private;
+        self
+    }
+    /// Create an AMP invoice, which can be paid spontaneously and more than once.
+    pub fn is_amp(mut self, is_amp: bool) -> Self {
+        self.body.is_amp = is_amp;
+        self
+    }
+    /// Preimage to use instead of one generated by LND.
+    pub fn r_preimage(mut self, preimage: Preimage) -> Self {
+        self.body.preimage = preimage.as_bytes().to_vec();
+        self
+    }
+    pub fn build(self) -> BrightResult<LndInvoiceRequestBody> {
+        let body = self.body;
+        if body.value > 0 && body.value_msat > 0 {
+            return Err(BrightError::invalid_input(
+                "Only one of value and value_msat can be set",
+            ));
+        }
+        body.amount_msat()?;
+        if body.is_amp && !body.preimage.is_empty() {
+            return Err(BrightError::invalid_input(
+                "AMP invoices cannot use a custom preimage",
+            ));
+        }
+        if body.fallback_addr.as_deref() == Some("") {
+            return Err(BrightError::invalid_input("Empty fallback address"));
+        }
+        Ok(body)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{LndInvoiceRequestBody, Preimage};
+
+    #[test]
+    fn serializes_lnd_types() -> anyhow::Result<()> {
+        let body = LndInvoiceRequestBody::builder()
+            .memo("Zap")
+            .value_msat(21_500)
+            .expiry(600)
+            .description_hash([0xab; 32])
+            .cltv_expiry(40)
+            .private(true)
+            .r_preimage(Preimage::from_bytes([0x01; 32]))
+            .build()?;
+        let json: serde_json::Value = serde_json::from_str(&body.to_string())?;
+        assert_eq!(json["value_msat"], "21500");
+        assert_eq!(json["expiry"], "600");
+        assert_eq!(json["cltv_expiry"], "40");
+        assert_eq!(json["private"], true);
+        assert_eq!(
+            json["description_hash"],
+            "q6urq6urq6urq6urq6urq6urq6urq6urq6urq6urq6s="
+        );
+        assert_eq!(
+            json["r_preimage"],
+            "AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE="
+        );
+        assert!(json.get("value").is_none());
+        assert!(json.get("is_amp").is_none());
+        assert_eq!(body.amount_msat()?, 21_500);
+
+        let simple = LndInvoiceRequestBody::new(1000, None);
+        assert_eq!(simple.to_string(), r#"{"value":"1000"}"#);
+        assert_eq!(simple.amount_msat()?, 1_000_000);
+        assert!(LndInvoiceRequestBody::new(u64::MAX, None)
+            .amount_msat()
+            .is_err());
+        assert!(LndInvoiceRequestBody::builder()
+            .value(u64::MAX / 1000 + 1)
+            .build()
+            .is_err());
+        Ok(())
+    }
+    #[test]
+    fn rejects_conflicting_options() {
+        assert!(LndInvoiceRequestBody::builder()
+            .value(1)
+            .value_msat(1000)
+            .build()
+            .is_err());
+        assert!(LndInvoiceRequestBody::builder()
+            .is_amp(true)
+            .r_preimage(Preimage::from_bytes([0; 32]))
+            .build()
+            .is_err());
     }
 }
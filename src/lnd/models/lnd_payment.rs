@@ -1,19 +1,94 @@
-use std::fmt::Display;
+use std::{collections::HashMap, fmt::Display};
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+use super::{
+    base64_bytes, is_default, optional, string_int, DecodedInvoice, LndHtlcAttempt, LndHtlcFailure,
+    NodePubkey, PaymentHash, Preimage,
+};
+use crate::{BrightError, BrightResult};
+
+/// Lists of int64s, which LND encodes as JSON strings.
+mod string_ints {
+    use serde::{ser::SerializeSeq, Deserialize, Deserializer, Serializer};
+
+    use super::string_int;
+
+    pub(crate) fn serialize<S: Serializer>(
+        values: &[u64],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(values.len()))?;
+        for value in values {
+            seq.serialize_element(&value.to_string())?;
+        }
+        seq.end()
+    }
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<u64>, D::Error> {
+        #[derive(Deserialize)]
+        struct StringInt(#[serde(with = "string_int")] u64);
+        Ok(Vec::<StringInt>::deserialize(deserializer)?
+            .into_iter()
+            .map(|value| value.0)
+            .collect())
+    }
+}
+
+/// Smallest record type LND accepts as a custom record.
+pub const MIN_CUSTOM_RECORD_TYPE: u64 = 65536;
+/// Record carrying the preimage of a keysend payment.
+pub const KEYSEND_RECORD_TYPE: u64 = 5482373484;
+const DEFAULT_TIMEOUT_SECONDS: i32 = 60;
+
+/// Body of the router's `SendPaymentV2` call on `/v2/router/send`.
+///
+/// Built with [`LndPaymentRequest::builder`], unset fields are left out.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
 pub struct LndPaymentRequest {
-    payment_request: String,  // String
-    timeout_seconds: i32,     // Int32
-    fee_limit_sat: String,    // Int64
-    allow_self_payment: bool, // Bool
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dest: Option<NodePubkey>,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    payment_request: String,
+    #[serde(with = "optional", skip_serializing_if = "Option::is_none")]
+    payment_hash: Option<PaymentHash>,
+    #[serde(with = "string_int", skip_serializing_if = "is_default")]
+    amt: u64,
+    #[serde(with = "string_int", skip_serializing_if = "is_default")]
+    amt_msat: u64,
+    timeout_seconds: i32,
+    #[serde(with = "string_int", skip_serializing_if = "is_default")]
+    fee_limit_sat: u64,
+    #[serde(with = "string_int", skip_serializing_if = "is_default")]
+    fee_limit_msat: u64,
+    #[serde(with = "string_ints", skip_serializing_if = "Vec::is_empty")]
+    outgoing_chan_ids: Vec<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_hop_pubkey: Option<NodePubkey>,
+    #[serde(skip_serializing_if = "is_default")]
+    cltv_limit: i32,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    allow_self_payment: bool,
+    #[serde(skip_serializing_if = "is_default")]
+    max_parts: u32,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    no_inflight_updates: bool,
+    #[serde(with = "string_int", skip_serializing_if = "is_default")]
+    max_shard_size_msat: u64,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    amp: bool,
+    #[serde(skip_serializing_if = "is_default")]
+    time_pref: f64,
+    #[serde(with = "base64_bytes::map", skip_serializing_if = "HashMap::is_empty")]
+    dest_custom_records: HashMap<u64, Vec<u8>>,
 }
 impl LndPaymentRequest {
     pub fn new(
         payment_request: String,
         timeout_seconds: i32,
-        fee_limit_sat: String,
+        fee_limit_sat: u64,
         allow_self_payment: bool,
     ) -> Self {
         Self {
@@ -21,21 +96,240 @@ impl LndPaymentRequest {
             timeout_seconds,
             fee_limit_sat,
             allow_self_payment,
+            ..Default::default()
+        }
+    }
+    /// Payment of the BOLT11 `payment_request`, timing out after a minute.
+    pub fn builder(payment_request: impl Into<String>) -> LndPaymentRequestBuilder {
+        LndPaymentRequestBuilder {
+            request: Self {
+                payment_request: payment_request.into(),
+                timeout_seconds: DEFAULT_TIMEOUT_SECONDS,
+                ..Default::default()
+            },
+        }
+    }
+    /// Spontaneous payment of `dest`, which learns `preimage` from the keysend
+    /// record. The amount must be set with [`LndPaymentRequestBuilder::amt_msat`].
+    pub fn keysend(dest: NodePubkey, preimage: Preimage) -> LndPaymentRequestBuilder {
+        let mut dest_custom_records = HashMap::new();
+        dest_custom_records.insert(KEYSEND_RECORD_TYPE, preimage.as_bytes().to_vec());
+        LndPaymentRequestBuilder {
+            request: Self {
+                dest: Some(dest),
+                payment_hash: Some(preimage.payment_hash()),
+                dest_custom_records,
+                timeout_seconds: DEFAULT_TIMEOUT_SECONDS,
+                ..Default::default()
+            },
+        }
+    }
+    pub fn payment_request(&self) -> &str {
+        &self.payment_request
+    }
+    pub fn dest(&self) -> Option<&NodePubkey> {
+        self.dest.as_ref()
+    }
+    /// Set for keysend payments, invoices carry their own.
+    pub fn payment_hash(&self) -> Option<PaymentHash> {
+        self.payment_hash
+    }
+    /// Amount override in millisatoshis, zero when the invoice sets the amount. An
+    /// error if `amt` overflows millisatoshis.
+    pub fn amount_msat(&self) -> BrightResult<u64> {
+        match self.amt_msat {
+            0 => self
+                .amt
+                .checked_mul(1000)
+                .ok_or_else(|| BrightError::invalid_input("Amount overflows millisatoshis")),
+            msat => Ok(msat),
         }
     }
+    pub fn dest_custom_records(&self) -> &HashMap<u64, Vec<u8>> {
+        &self.dest_custom_records
+    }
 }
-impl ToString for LndPaymentRequest {
-    fn to_string(&self) -> String {
-        serde_json::to_string(self).unwrap()
+
+#[derive(Debug, Clone)]
+pub struct LndPaymentRequestBuilder {
+    request: LndPaymentRequest,
+}
+impl LndPaymentRequestBuilder {
+    /// Amount in satoshis, only for keysend and zero amount invoices. Cannot be
+    /// combined with [`Self::amt_msat`].
+    pub fn amt(mut self, sat: u64) -> Self {
+        self.request.amt = sat;
+        self
+    }
+    /// Amount in millisatoshis, only for keysend and zero amount invoices. Cannot
+    /// be combined with [`Self::amt`].
+    pub fn amt_msat(mut self, msat: u64) -> Self {
+        self.request.amt_msat = msat;
+        self
+    }
+    /// How long the router keeps trying to find a route.
+    pub fn timeout_seconds(mut self, seconds: u32) -> Self {
+        self.request.timeout_seconds = seconds.min(i32::MAX as u32) as i32;
+        self
+    }
+    /// Most routing fee to pay in satoshis. Cannot be combined with
+    /// [`Self::fee_limit_msat`], LND pays no fee unless one of them is set.
+    pub fn fee_limit_sat(mut self, sat: u64) -> Self {
+        self.request.fee_limit_sat = sat;
+        self
+    }
+    /// Most routing fee to pay in millisatoshis. Cannot be combined with
+    /// [`Self::fee_limit_sat`].
+    pub fn fee_limit_msat(mut self, msat: u64) -> Self {
+        self.request.fee_limit_msat = msat;
+        self
+    }
+    /// Restricts the first hop to this channel, can be given more than once.
+    pub fn outgoing_chan_id(mut self, chan_id: u64) -> Self {
+        self.request.outgoing_chan_ids.push(chan_id);
+        self
+    }
+    /// Node the payment must reach the destination through.
+    pub fn last_hop_pubkey(mut self, pubkey: NodePubkey) -> Self {
+        self.request.last_hop_pubkey = Some(pubkey);
+        self
+    }
+    /// Most blocks the payment may be locked up for.
+    pub fn cltv_limit(mut self, blocks: u32) -> Self {
+        self.request.cltv_limit = blocks.min(i32::MAX as u32) as i32;
+        self
+    }
+    /// Allow paying an invoice of this node through a circular route.
+    pub fn allow_self_payment(mut self, allow_self_payment: bool) -> Self {
+        self.request.allow_self_payment = allow_self_payment;
+        self
+    }
+    /// Most parts the payment may be split into, one disables multi-path payments.
+    pub fn max_parts(mut self, parts: u32) -> Self {
+        self.request.max_parts = parts;
+        self
+    }
+    /// Largest part of a multi-path payment.
+    pub fn max_shard_size_msat(mut self, msat: u64) -> Self {
+        self.request.max_shard_size_msat = msat;
+        self
+    }
+    /// Only send the final status update.
+    pub fn no_inflight_updates(mut self, no_inflight_updates: bool) -> Self {
+        self.request.no_inflight_updates = no_inflight_updates;
+        self
+    }
+    /// TLV record for the destination, with a type of at least
+    /// [`MIN_CUSTOM_RECORD_TYPE`].
+    pub fn dest_custom_record(mut self, record_type: u64, value: impl Into<Vec<u8>>) -> Self {
+        self.request
+            .dest_custom_records
+            .insert(record_type, value.into());
+        self
+    }
+    /// Route preference between -1 for lowest fees and 1 for highest reliability.
+    pub fn time_pref(mut self, time_pref: f64) -> Self {
+        self.request.time_pref = time_pref;
+        self
+    }
+    /// Send the payment as an atomic multi-path payment.
+    pub fn amp(mut self, amp: bool) -> Self {
+        self.request.amp = amp;
+        self
+    }
+    pub fn build(self) -> BrightResult<LndPaymentRequest> {
+        let request = self.request;
+        if request.amt > 0 && request.amt_msat > 0 {
+            return Err(BrightError::invalid_input(
+                "Only one of amt and amt_msat can be set",
+            ));
+        }
+        if request.fee_limit_sat > 0 && request.fee_limit_msat > 0 {
+            return Err(BrightError::invalid_input(
+                "Only one of fee_limit_sat and fee_limit_msat can be set",
+            ));
+        }
+        if request.timeout_seconds <= 0 {
+            return Err(BrightError::invalid_input("Timeout must be positive"));
+        }
+        if !(-1.0..=1.0).contains(&request.time_pref) {
+            return Err(BrightError::invalid_input(
+                "Time preference must be between -1 and 1",
+            ));
+        }
+        if request.max_parts == 1 && request.max_shard_size_msat > 0 {
+            return Err(BrightError::invalid_input(
+                "Shard size cannot be limited for single part payments",
+            ));
+        }
+        if let Some(record_type) = request
+            .dest_custom_records
+            .keys()
+            .find(|record_type| **record_type < MIN_CUSTOM_RECORD_TYPE)
+        {
+            return Err(BrightError::invalid_input(format!(
+                "Custom record type {record_type} is below {MIN_CUSTOM_RECORD_TYPE}"
+            )));
+        }
+        if request.payment_request.is_empty() {
+            return Self::validate_keysend(request);
+        }
+        if request.dest.is_some() || request.payment_hash.is_some() {
+            return Err(BrightError::invalid_input(
+                "Invoice payments cannot set a destination or payment hash",
+            ));
+        }
+        let invoice = request.payment_request.parse::<DecodedInvoice>()?;
+        match (invoice.amount_msat(), request.amount_msat()?) {
+            (Some(_), msat) if msat > 0 => Err(BrightError::invalid_input(
+                "Amount can only be set for zero amount invoices",
+            )),
+            (None, 0) => Err(BrightError::invalid_input(
+                "Zero amount invoices need an amount",
+            )),
+            _ => Ok(request),
+        }
+    }
+    fn validate_keysend(request: LndPaymentRequest) -> BrightResult<LndPaymentRequest> {
+        let (Some(_), Some(payment_hash)) = (request.dest, request.payment_hash) else {
+            return Err(BrightError::invalid_input(
+                "Payments need a payment request or a destination and payment hash",
+            ));
+        };
+        let preimage = request
+            .dest_custom_records
+            .get(&KEYSEND_RECORD_TYPE)
+            .and_then(|record| Preimage::try_from(record.as_slice()).ok());
+        if !preimage.is_some_and(|preimage| preimage.matches(&payment_hash)) {
+            return Err(BrightError::invalid_input(
+                "Keysend record does not match the payment hash",
+            ));
+        }
+        if request.amp {
+            return Err(BrightError::invalid_input(
+                "Keysend payments cannot be sent as AMP",
+            ));
+        }
+        if request.amount_msat()? == 0 {
+            return Err(BrightError::invalid_input(
+                "Keysend payments need an amount",
+            ));
+        }
+        Ok(request)
+    }
+}
+impl Display for LndPaymentRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", serde_json::to_string(self).unwrap())
     }
 }
-impl Into<String> for LndPaymentRequest {
-    fn into(self) -> String {
-        serde_json::to_string(&self).unwrap()
+impl From<LndPaymentRequest> for String {
+    fn from(value: LndPaymentRequest) -> Self {
+        serde_json::to_string(&value).unwrap()
     }
 }
 impl TryFrom<String> for LndPaymentRequest {
-    type Error = anyhow::Error;
+    type Error = BrightError;
     fn try_from(value: String) -> Result<Self, Self::Error> {
         Ok(serde_json::from_str(&value)?)
     }
@@ -57,27 +351,132 @@ impl Display for InvoicePaymentState {
     }
 }
 
+/// Why the router gave up on a payment.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum LndPaymentFailureReason {
+    #[default]
+    #[serde(rename = "FAILURE_REASON_NONE")]
+    None,
+    #[serde(rename = "FAILURE_REASON_TIMEOUT")]
+    Timeout,
+    #[serde(rename = "FAILURE_REASON_NO_ROUTE")]
+    NoRoute,
+    #[serde(rename = "FAILURE_REASON_ERROR")]
+    Error,
+    #[serde(rename = "FAILURE_REASON_INCORRECT_PAYMENT_DETAILS")]
+    IncorrectPaymentDetails,
+    #[serde(rename = "FAILURE_REASON_INSUFFICIENT_BALANCE")]
+    InsufficientBalance,
+    #[serde(rename = "FAILURE_REASON_CANCELED")]
+    Canceled,
+}
+impl Display for LndPaymentFailureReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match self {
+            LndPaymentFailureReason::None => "no failure",
+            LndPaymentFailureReason::Timeout => "payment timed out",
+            LndPaymentFailureReason::NoRoute => "no route found",
+            LndPaymentFailureReason::Error => "unexpected error",
+            LndPaymentFailureReason::IncorrectPaymentDetails => "incorrect payment details",
+            LndPaymentFailureReason::InsufficientBalance => "insufficient balance",
+            LndPaymentFailureReason::Canceled => "payment canceled",
+        };
+        write!(f, "{}", reason)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LndPaymentResponse {
-    payment_preimage: String,
+    #[serde(default)]
+    payment_hash: PaymentHash,
+    #[serde(with = "optional", default)]
+    payment_preimage: Option<Preimage>,
+    #[serde(default)]
+    payment_request: String,
+    #[serde(with = "string_int", default)]
+    value_sat: u64,
+    #[serde(with = "string_int", default)]
+    value_msat: u64,
+    #[serde(with = "string_int", default)]
+    fee_sat: u64,
+    #[serde(with = "string_int", default)]
+    fee_msat: u64,
+    #[serde(with = "string_int", default)]
+    creation_time_ns: i64,
+    #[serde(with = "string_int", default)]
+    payment_index: u64,
     status: InvoicePaymentState,
+    #[serde(default)]
+    failure_reason: LndPaymentFailureReason,
+    #[serde(default)]
+    htlcs: Vec<LndHtlcAttempt>,
 }
 impl LndPaymentResponse {
-    pub fn preimage(&self) -> String {
-        self.payment_preimage.clone()
+    pub fn payment_hash(&self) -> PaymentHash {
+        self.payment_hash
+    }
+    /// Preimage proving the payment, set once it has succeeded.
+    pub fn preimage(&self) -> Option<Preimage> {
+        self.payment_preimage
+    }
+    /// Empty for payments sent without an invoice, such as keysend.
+    pub fn payment_request(&self) -> &str {
+        &self.payment_request
+    }
+    pub fn value_sat(&self) -> u64 {
+        self.value_sat
+    }
+    pub fn value_msat(&self) -> u64 {
+        self.value_msat
+    }
+    /// Routing fee paid so far, rounded down to whole satoshis.
+    pub fn fee_sat(&self) -> u64 {
+        self.fee_sat
+    }
+    /// Routing fee paid so far.
+    pub fn fee_msat(&self) -> u64 {
+        self.fee_msat
+    }
+    pub fn creation_time_ns(&self) -> i64 {
+        self.creation_time_ns
+    }
+    /// Unix timestamp at which the payment was started.
+    pub fn created_at(&self) -> u64 {
+        (self.creation_time_ns / 1_000_000_000).max(0) as u64
+    }
+    /// Index of the payment in LND's database, used to page through payments.
+    pub fn payment_index(&self) -> u64 {
+        self.payment_index
     }
     pub fn status(&self) -> InvoicePaymentState {
         self.status.clone()
     }
+    pub fn failure_reason(&self) -> LndPaymentFailureReason {
+        self.failure_reason
+    }
+    pub fn htlcs(&self) -> &[LndHtlcAttempt] {
+        &self.htlcs
+    }
+    /// Failures of the attempts that did not go through.
+    pub fn htlc_failures(&self) -> impl Iterator<Item = &LndHtlcFailure> {
+        self.htlcs.iter().filter_map(|htlc| htlc.failure.as_ref())
+    }
+    /// Whether the payment has succeeded or failed for good.
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self.status,
+            InvoicePaymentState::Succeeded | InvoicePaymentState::Failed
+        )
+    }
 }
 impl TryFrom<String> for LndPaymentResponse {
-    type Error = anyhow::Error;
+    type Error = BrightError;
     fn try_from(value: String) -> Result<Self, Self::Error> {
         Ok(serde_json::from_str(&value)?)
     }
 }
 impl TryInto<String> for LndPaymentResponse {
-    type Error = anyhow::Error;
+    type Error = BrightError;
     fn try_into(self) -> Result<String, Self::Error> {
         Ok(serde_json::to_string(&self)?)
     }
@@ -87,3 +486,340 @@ impl Display for LndPaymentResponse {
         write!(f, "{}", serde_json::to_string_pretty(self).unwrap())
     }
 }
+
+/// Page of payments from `/v1/payments`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LndPaymentList {
+    pub payments: Vec<LndPaymentResponse>,
+    #[serde(with = "string_int")]
+    pub first_index_offset: u64,
+    #[serde(with = "string_int")]
+    pub last_index_offset: u64,
+    /// Set when the query asked for [`ListPaymentsQuery::count_total_payments`].
+    #[serde(with = "string_int")]
+    pub total_num_payments: u64,
+}
+impl TryFrom<String> for LndPaymentList {
+    type Error = BrightError;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Ok(serde_json::from_str(&value)?)
+    }
+}
+impl From<LndPaymentList> for String {
+    fn from(value: LndPaymentList) -> Self {
+        serde_json::to_string(&value).unwrap()
+    }
+}
+
+/// Filters and paging for `/v1/payments`.
+///
+/// Payments are paged by their `payment_index`, like invoices in
+/// [`crate::ListInvoicesQuery`]. Only succeeded payments are listed unless
+/// [`Self::include_incomplete`] is set.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(default)]
+pub struct ListPaymentsQuery {
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    include_incomplete: bool,
+    #[serde(skip_serializing_if = "is_default")]
+    index_offset: u64,
+    #[serde(skip_serializing_if = "is_default")]
+    max_payments: u64,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    reversed: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    count_total_payments: bool,
+    #[serde(skip_serializing_if = "is_default")]
+    creation_date_start: u64,
+    #[serde(skip_serializing_if = "is_default")]
+    creation_date_end: u64,
+}
+impl ListPaymentsQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Also list in-flight and failed payments.
+    pub fn include_incomplete(mut self, include_incomplete: bool) -> Self {
+        self.include_incomplete = include_incomplete;
+        self
+    }
+    pub fn index_offset(mut self, index_offset: u64) -> Self {
+        self.index_offset = index_offset;
+        self
+    }
+    /// Page size, LND defaults to 100.
+    pub fn max_payments(mut self, max_payments: u64) -> Self {
+        self.max_payments = max_payments;
+        self
+    }
+    pub fn reversed(mut self, reversed: bool) -> Self {
+        self.reversed = reversed;
+        self
+    }
+    /// Count the payments matching the query, which is slow on large databases.
+    pub fn count_total_payments(mut self, count_total_payments: bool) -> Self {
+        self.count_total_payments = count_total_payments;
+        self
+    }
+    /// Unix timestamp, inclusive.
+    pub fn creation_date_start(mut self, timestamp: u64) -> Self {
+        self.creation_date_start = timestamp;
+        self
+    }
+    /// Unix timestamp, inclusive.
+    pub fn creation_date_end(mut self, timestamp: u64) -> Self {
+        self.creation_date_end = timestamp;
+        self
+    }
+    /// Query for the page following `page`, in the same direction.
+    pub fn next_page(self, page: &LndPaymentList) -> Self {
+        match self.reversed {
+            true => self.index_offset(page.first_index_offset),
+            false => self.index_offset(page.last_index_offset),
+        }
+    }
+}
+
+/// Payment that went through.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaymentSuccess {
+    pub payment_hash: PaymentHash,
+    pub preimage: Preimage,
+    pub value_msat: u64,
+    pub fee_msat: u64,
+    pub htlcs: Vec<LndHtlcAttempt>,
+}
+
+/// Payment the router gave up on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaymentFailure {
+    pub payment_hash: PaymentHash,
+    pub reason: LndPaymentFailureReason,
+    pub htlcs: Vec<LndHtlcAttempt>,
+}
+
+/// Final result of a payment sent through LND's router.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaymentOutcome {
+    Succeeded(PaymentSuccess),
+    Failed(PaymentFailure),
+}
+impl PaymentOutcome {
+    pub fn is_success(&self) -> bool {
+        matches!(self, PaymentOutcome::Succeeded(_))
+    }
+    pub fn payment_hash(&self) -> PaymentHash {
+        match self {
+            PaymentOutcome::Succeeded(success) => success.payment_hash,
+            PaymentOutcome::Failed(failure) => failure.payment_hash,
+        }
+    }
+    pub fn preimage(&self) -> Option<Preimage> {
+        match self {
+            PaymentOutcome::Succeeded(success) => Some(success.preimage),
+            PaymentOutcome::Failed(_) => None,
+        }
+    }
+    pub fn htlcs(&self) -> &[LndHtlcAttempt] {
+        match self {
+            PaymentOutcome::Succeeded(success) => &success.htlcs,
+            PaymentOutcome::Failed(failure) => &failure.htlcs,
+        }
+    }
+}
+impl TryFrom<LndPaymentResponse> for PaymentOutcome {
+    type Error = BrightError;
+    fn try_from(value: LndPaymentResponse) -> Result<Self, Self::Error> {
+        match (value.status, value.payment_preimage) {
+            (InvoicePaymentState::Succeeded, Some(preimage)) => {
+                Ok(PaymentOutcome::Succeeded(PaymentSuccess {
+                    payment_hash: value.payment_hash,
+                    preimage,
+                    value_msat: value.value_msat,
+                    fee_msat: value.fee_msat,
+                    htlcs: value.htlcs,
+                }))
+            }
+            (InvoicePaymentState::Failed, _) => Ok(PaymentOutcome::Failed(PaymentFailure {
+                payment_hash: value.payment_hash,
+                reason: value.failure_reason,
+                htlcs: value.htlcs,
+            })),
+            (InvoicePaymentState::Succeeded, None) => Err(BrightError::invalid_input(
+                "Succeeded payment without a preimage",
+            )),
+            (status, _) => Err(BrightError::invalid_input(format!(
+                "Payment is not final: {:?}",
+                status
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        InvoicePaymentState, LndPaymentFailureReason, LndPaymentRequest, LndPaymentResponse,
+        KEYSEND_RECORD_TYPE,
+    };
+    use crate::{testing::mock_bolt11, LndFailureCode, PaymentHash, Preimage};
+
+    const PUBKEY: &str = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+
+    #[test]
+    fn serializes_router_options() -> anyhow::Result<()> {
+        let amountless = mock_bolt11(&PaymentHash::from_bytes([0x01; 32]), 0, "Tip");
+        let request = LndPaymentRequest::builder(&amountless)
+            .amt_msat(2_500)
+            .fee_limit_msat(100)
+            .outgoing_chan_id(123)
+            .outgoing_chan_id(456)
+            .last_hop_pubkey(PUBKEY.parse()?)
+            .cltv_limit(500)
+            .max_parts(4)
+            .max_shard_size_msat(1_000)
+            .no_inflight_updates(true)
+            .dest_custom_record(70_000, b"hi".to_vec())
+            .time_pref(0.5)
+            .build()?;
+        let json: serde_json::Value = serde_json::from_str(&request.to_string())?;
+        assert_eq!(json["amt_msat"], "2500");
+        assert_eq!(json["fee_limit_msat"], "100");
+        assert_eq!(json["timeout_seconds"], 60);
+        assert_eq!(json["outgoing_chan_ids"], serde_json::json!(["123", "456"]));
+        assert_eq!(
+            json["last_hop_pubkey"],
+            "Anm+Zn753LusVaBilc6HCwcCm/zbLc4o2VnygVsW+BeY"
+        );
+        assert_eq!(json["cltv_limit"], 500);
+        assert_eq!(json["max_parts"], 4);
+        assert_eq!(json["max_shard_size_msat"], "1000");
+        assert_eq!(json["dest_custom_records"]["70000"], "aGk=");
+        assert_eq!(json["time_pref"], 0.5);
+        assert!(json.get("amt").is_none());
+        assert!(json.get("fee_limit_sat").is_none());
+        assert!(json.get("amp").is_none());
+        assert_eq!(LndPaymentRequest::try_from(request.to_string())?, request);
+        assert_eq!(request.amount_msat()?, 2_500);
+        let null_records: LndPaymentRequest =
+            serde_json::from_str(r#"{"payment_request":"lnbc1","dest_custom_records":null}"#)?;
+        assert!(null_records.dest_custom_records().is_empty());
+        Ok(())
+    }
+    #[test]
+    fn builds_keysend_requests() -> anyhow::Result<()> {
+        let preimage = Preimage::from_bytes([0x07; 32]);
+        let keysend = || LndPaymentRequest::keysend(PUBKEY.parse().unwrap(), preimage);
+        let request = keysend().amt_msat(21_000).build()?;
+        let json: serde_json::Value = serde_json::from_str(&request.to_string())?;
+        assert_eq!(json["dest"], "Anm+Zn753LusVaBilc6HCwcCm/zbLc4o2VnygVsW+BeY");
+        assert_eq!(json["payment_hash"], preimage.payment_hash().to_base64());
+        assert_eq!(
+            json["dest_custom_records"][KEYSEND_RECORD_TYPE.to_string()],
+            preimage.to_base64()
+        );
+        assert!(json.get("payment_request").is_none());
+        assert_eq!(request.payment_hash(), Some(preimage.payment_hash()));
+
+        assert!(keysend().build().is_err());
+        assert!(keysend().amt_msat(1).amp(true).build().is_err());
+        assert!(keysend()
+            .amt_msat(1)
+            .dest_custom_record(KEYSEND_RECORD_TYPE, vec![0; 32])
+            .build()
+            .is_err());
+        assert!(LndPaymentRequest::builder("").amt(1).build().is_err());
+        Ok(())
+    }
+    #[test]
+    fn rejects_contradictory_options() {
+        let invoice = mock_bolt11(&PaymentHash::from_bytes([0x02; 32]), 1_000, "Fixed");
+        let amountless = mock_bolt11(&PaymentHash::from_bytes([0x03; 32]), 0, "Tip");
+        let builder = || LndPaymentRequest::builder(&invoice);
+        assert!(builder().build().is_ok());
+        assert!(builder().amt(1).build().is_err());
+        assert!(LndPaymentRequest::builder(&amountless).build().is_err());
+        assert!(LndPaymentRequest::builder(&amountless)
+            .amt(u64::MAX)
+            .build()
+            .is_err());
+        assert!(LndPaymentRequest::builder(&amountless)
+            .amt(1)
+            .amt_msat(1_000)
+            .build()
+            .is_err());
+        assert!(builder()
+            .fee_limit_sat(1)
+            .fee_limit_msat(1)
+            .build()
+            .is_err());
+        assert!(builder().timeout_seconds(0).build().is_err());
+        assert!(builder().time_pref(1.5).build().is_err());
+        assert!(builder().time_pref(f64::NAN).build().is_err());
+        assert!(builder()
+            .max_parts(1)
+            .max_shard_size_msat(100)
+            .build()
+            .is_err());
+        assert!(builder()
+            .dest_custom_record(34_349_334, b"chat".to_vec())
+            .build()
+            .is_ok());
+        assert!(builder()
+            .dest_custom_record(5, b"low".to_vec())
+            .build()
+            .is_err());
+        assert!(LndPaymentRequest::builder("lnbc1").build().is_err());
+    }
+    #[test]
+    fn deserializes_full_payment() -> anyhow::Result<()> {
+        let payment = LndPaymentResponse::try_from(
+            serde_json::json!({
+                "payment_hash": "42".repeat(32),
+                "value": "100",
+                "creation_date": "1700000000",
+                "fee": "1",
+                "payment_preimage": "0".repeat(64),
+                "value_sat": "100",
+                "value_msat": "100000",
+                "payment_request": "lnbcrt1mock",
+                "status": "FAILED",
+                "fee_sat": "1",
+                "fee_msat": "1500",
+                "creation_time_ns": "1700000000123456789",
+                "htlcs": [{
+                    "attempt_id": "1",
+                    "status": "FAILED",
+                    "route": { "hops": [{ "chan_id": "1", "pub_key": "02aa" }] },
+                    "failure": { "code": "INCORRECT_OR_UNKNOWN_PAYMENT_DETAILS", "failure_source_index": 1 }
+                }],
+                "payment_index": "12",
+                "failure_reason": "FAILURE_REASON_INCORRECT_PAYMENT_DETAILS"
+            })
+            .to_string(),
+        )?;
+        assert_eq!(payment.payment_hash(), PaymentHash::from_bytes([0x42; 32]));
+        assert!(payment.preimage().is_none());
+        assert_eq!(payment.value_sat(), 100);
+        assert_eq!(payment.value_msat(), 100_000);
+        assert_eq!(payment.fee_sat(), 1);
+        assert_eq!(payment.fee_msat(), 1_500);
+        assert_eq!(payment.created_at(), 1_700_000_000);
+        assert_eq!(payment.payment_index(), 12);
+        assert_eq!(payment.payment_request(), "lnbcrt1mock");
+        assert_eq!(payment.status(), InvoicePaymentState::Failed);
+        assert_eq!(
+            payment.failure_reason(),
+            LndPaymentFailureReason::IncorrectPaymentDetails
+        );
+        assert_eq!(
+            payment
+                .htlc_failures()
+                .map(|failure| failure.code)
+                .collect::<Vec<_>>(),
+            vec![LndFailureCode::IncorrectOrUnknownPaymentDetails]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,64 @@
+use base64::prelude::*;
+
+use super::{bytes32, PaymentHash};
+use crate::BrightResult;
+
+/// Identifies an invoice for `/v2/invoices/lookup`.
+///
+/// The constructors take raw bytes, hex or base64.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InvoiceLookup {
+    PaymentHash(PaymentHash),
+    PaymentAddr([u8; 32]),
+    /// Payment set of an AMP invoice.
+    SetId([u8; 32]),
+}
+impl InvoiceLookup {
+    pub fn payment_hash(value: impl AsRef<[u8]>) -> BrightResult<Self> {
+        Ok(Self::PaymentHash(PaymentHash::from_bytes(bytes32(
+            value.as_ref(),
+        )?)))
+    }
+    pub fn payment_addr(value: impl AsRef<[u8]>) -> BrightResult<Self> {
+        Ok(Self::PaymentAddr(bytes32(value.as_ref())?))
+    }
+    pub fn set_id(value: impl AsRef<[u8]>) -> BrightResult<Self> {
+        Ok(Self::SetId(bytes32(value.as_ref())?))
+    }
+    /// Query parameter for the lookup, base64url encoded.
+    pub fn query(&self) -> (&'static str, String) {
+        match self {
+            Self::PaymentHash(hash) => ("payment_hash", hash.to_base64_url()),
+            Self::PaymentAddr(addr) => ("payment_addr", BASE64_URL_SAFE.encode(addr)),
+            Self::SetId(set_id) => ("set_id", BASE64_URL_SAFE.encode(set_id)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{bytes32, InvoiceLookup};
+
+    #[test]
+    fn accepts_any_encoding() -> anyhow::Result<()> {
+        let raw = [0xde, 0xad, 0xbe, 0xef].repeat(8);
+        let hex = "deadbeef".repeat(8);
+        let base64 = "3q2+796tvu/erb7v3q2+796tvu/erb7v3q2+796tvu8=";
+        let base64_url = "3q2-796tvu_erb7v3q2-796tvu_erb7v3q2-796tvu8";
+        for value in [
+            &raw[..],
+            hex.as_bytes(),
+            base64.as_bytes(),
+            base64_url.as_bytes(),
+        ] {
+            assert_eq!(bytes32(value)?.to_vec(), raw);
+        }
+        assert_eq!(
+            InvoiceLookup::payment_addr(&hex)?.query(),
+            ("payment_addr", base64_url.to_string() + "=")
+        );
+        assert!(bytes32(b"deadbeef").is_err());
+        assert!(InvoiceLookup::payment_hash("zz".repeat(32)).is_err());
+        Ok(())
+    }
+}
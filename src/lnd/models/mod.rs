@@ -1,20 +1,41 @@
+mod balance;
+pub(crate) mod base64_bytes;
+mod decoded;
+mod hash;
 mod hodl_invoice;
+mod htlc;
 mod info;
 mod invoice;
 mod invoice_request;
 mod lnd_payment;
+mod lookup;
 mod onchain;
+mod pubkey;
+pub(crate) mod string_int;
+pub use balance::*;
+pub use decoded::*;
+pub use hash::*;
 pub use hodl_invoice::*;
+pub use htlc::*;
 pub use info::*;
 pub use invoice::*;
 pub use invoice_request::*;
 pub use lnd_payment::*;
+pub use lookup::*;
 pub use onchain::*;
+pub use pubkey::*;
 
 use std::fmt::Display;
 
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
+use crate::{BrightError, GrpcStatus};
+
+/// `skip_serializing_if` predicate leaving out fields LND treats as unset.
+pub(crate) fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LndResponse<T> {
     pub result: T,
@@ -31,7 +52,7 @@ impl<T> TryFrom<&String> for LndResponse<T>
 where
     T: Serialize + DeserializeOwned + Clone + 'static,
 {
-    type Error = anyhow::Error;
+    type Error = BrightError;
     fn try_from(value: &String) -> Result<Self, Self::Error> {
         Ok(serde_json::from_str(value)?)
     }
@@ -40,7 +61,7 @@ impl<T> TryFrom<String> for LndResponse<T>
 where
     T: Serialize + DeserializeOwned + Clone + 'static,
 {
-    type Error = anyhow::Error;
+    type Error = BrightError;
     fn try_from(value: String) -> Result<Self, Self::Error> {
         Ok(serde_json::from_str(&value)?)
     }
@@ -49,7 +70,7 @@ impl<T> TryInto<String> for LndResponse<T>
 where
     T: Serialize + DeserializeOwned + Clone + 'static,
 {
-    type Error = anyhow::Error;
+    type Error = BrightError;
     fn try_into(self) -> Result<String, Self::Error> {
         Ok(serde_json::to_string(&self)?)
     }
@@ -67,29 +88,88 @@ where
 pub struct LndErrorDetail {
     code: i32,
     message: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    details: Vec<serde_json::Value>,
+}
+impl LndErrorDetail {
+    pub fn code(&self) -> i32 {
+        self.code
+    }
+    pub fn status(&self) -> GrpcStatus {
+        GrpcStatus::from(self.code)
+    }
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+    pub fn details(&self) -> &[serde_json::Value] {
+        &self.details
+    }
 }
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LndError {
     error: LndErrorDetail,
 }
 impl LndError {
-    pub fn timeout() -> Self {
+    pub fn new(status: GrpcStatus, message: impl Into<String>) -> Self {
         Self {
             error: LndErrorDetail {
-                code: 0,
-                message: "Timeout".to_string(),
+                code: status.code(),
+                message: message.into(),
+                details: vec![],
             },
         }
     }
+    /// Builds the error for a failed REST call from its HTTP status and body.
+    ///
+    /// LND answers REST errors with `{"code", "message", "details"}`, while errors on
+    /// streams are wrapped in `{"error": {...}}`. Bodies in neither shape are mapped
+    /// from the HTTP status.
+    pub fn from_response(http_status: u16, body: &str) -> Self {
+        if let Ok(error) = serde_json::from_str::<LndErrorDetail>(body) {
+            return Self { error };
+        }
+        if let Ok(error) = serde_json::from_str::<LndError>(body) {
+            return error;
+        }
+        let status = match http_status {
+            400 => GrpcStatus::InvalidArgument,
+            401 => GrpcStatus::Unauthenticated,
+            403 => GrpcStatus::PermissionDenied,
+            404 => GrpcStatus::NotFound,
+            409 => GrpcStatus::AlreadyExists,
+            429 => GrpcStatus::ResourceExhausted,
+            501 => GrpcStatus::Unimplemented,
+            503 => GrpcStatus::Unavailable,
+            504 => GrpcStatus::DeadlineExceeded,
+            _ => GrpcStatus::Unknown,
+        };
+        let message = match body.trim() {
+            "" => format!("HTTP status {}", http_status),
+            body => body.to_string(),
+        };
+        Self::new(status, message)
+    }
+    pub fn timeout() -> Self {
+        Self::new(GrpcStatus::DeadlineExceeded, "Timeout")
+    }
+    pub fn detail(&self) -> &LndErrorDetail {
+        &self.error
+    }
+    pub fn status(&self) -> GrpcStatus {
+        self.error.status()
+    }
+    pub fn message(&self) -> &str {
+        self.error.message()
+    }
 }
 impl TryFrom<&String> for LndError {
-    type Error = anyhow::Error;
+    type Error = BrightError;
     fn try_from(value: &String) -> Result<Self, Self::Error> {
         Ok(serde_json::from_str(value)?)
     }
 }
 impl TryFrom<String> for LndError {
-    type Error = anyhow::Error;
+    type Error = BrightError;
     fn try_from(value: String) -> Result<Self, Self::Error> {
         Ok(serde_json::from_str(&value)?)
     }
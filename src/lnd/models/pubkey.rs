@@ -0,0 +1,108 @@
+use std::{fmt::Display, str::FromStr};
+
+use base64::prelude::*;
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::base64_bytes;
+use crate::{BrightError, BrightResult};
+
+/// Compressed public key of a lightning node.
+///
+/// Parsed from hex as shown by `getinfo` and checked to be a point on the curve,
+/// serialized as base64 like the other bytes fields of LND's REST API.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodePubkey([u8; 33]);
+impl NodePubkey {
+    pub fn from_bytes(bytes: &[u8]) -> BrightResult<Self> {
+        let bytes = <[u8; 33]>::try_from(bytes).map_err(|_| {
+            BrightError::invalid_input(format!(
+                "Expected a 33 byte public key, got {} bytes",
+                bytes.len()
+            ))
+        })?;
+        #[cfg(not(target_arch = "wasm32"))]
+        secp256k1::PublicKey::from_slice(&bytes)
+            .map_err(|e| BrightError::invalid_input(format!("Invalid public key: {e}")))?;
+        #[cfg(target_arch = "wasm32")]
+        if !matches!(bytes[0], 0x02 | 0x03) {
+            return Err(BrightError::invalid_input(
+                "Invalid public key: not compressed",
+            ));
+        }
+        Ok(Self(bytes))
+    }
+    pub fn as_bytes(&self) -> &[u8; 33] {
+        &self.0
+    }
+    pub fn to_hex(&self) -> String {
+        self.0.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+}
+impl FromStr for NodePubkey {
+    type Err = BrightError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.len() != 66 || !s.is_ascii() {
+            return Err(BrightError::invalid_input("Expected 66 hex characters"));
+        }
+        let bytes = (0..66)
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| BrightError::invalid_input(format!("Invalid hex: {e}")))?;
+        Self::from_bytes(&bytes)
+    }
+}
+impl Display for NodePubkey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_hex())
+    }
+}
+impl std::fmt::Debug for NodePubkey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "NodePubkey({})", self.to_hex())
+    }
+}
+impl Serialize for NodePubkey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&BASE64_STANDARD.encode(self.0))
+    }
+}
+impl<'de> Deserialize<'de> for NodePubkey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        match value.len() {
+            66 => value.parse(),
+            _ => base64_bytes::decode(&value)
+                .map_err(|e| BrightError::invalid_input(format!("Invalid base64: {e}")))
+                .and_then(|bytes| Self::from_bytes(&bytes)),
+        }
+        .map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::NodePubkey;
+
+    const PUBKEY: &str = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+
+    #[test]
+    fn parses_and_validates_pubkeys() -> anyhow::Result<()> {
+        let pubkey: NodePubkey = PUBKEY.parse()?;
+        assert_eq!(pubkey.to_string(), PUBKEY);
+        let json = serde_json::to_string(&pubkey)?;
+        assert_eq!(serde_json::from_str::<NodePubkey>(&json)?, pubkey);
+        assert_eq!(
+            serde_json::from_str::<NodePubkey>(&format!("\"{PUBKEY}\""))?,
+            pubkey
+        );
+
+        assert!(PUBKEY[..64].parse::<NodePubkey>().is_err());
+        assert!(format!("04{}", &PUBKEY[2..]).parse::<NodePubkey>().is_err());
+        assert!(format!("02{}", "f".repeat(64))
+            .parse::<NodePubkey>()
+            .is_err());
+        Ok(())
+    }
+}
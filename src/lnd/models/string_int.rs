@@ -0,0 +1,33 @@
+//! Serde helpers for the 64 bit integers LND encodes as JSON strings.
+//!
+//! Use with `#[serde(with = "string_int")]`. Deserializing also accepts plain numbers.
+use std::{fmt::Display, str::FromStr};
+
+use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StringOrNumber {
+    String(String),
+    Number(serde_json::Number),
+}
+
+pub(crate) fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Display,
+    S: Serializer,
+{
+    serializer.collect_str(value)
+}
+
+pub(crate) fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: FromStr,
+    T::Err: Display,
+    D: Deserializer<'de>,
+{
+    match StringOrNumber::deserialize(deserializer)? {
+        StringOrNumber::String(value) => value.parse().map_err(D::Error::custom),
+        StringOrNumber::Number(value) => value.to_string().parse().map_err(D::Error::custom),
+    }
+}
@@ -0,0 +1,621 @@
+use std::{fmt::Debug, sync::Arc, time::Duration};
+
+use futures_util::{stream, Stream, StreamExt, TryStreamExt};
+
+use super::{LightningClient, LndWebsocket, LndWebsocketMessage};
+use crate::{
+    BrightError, BrightResult, DecodedInvoice, GrpcStatus, ListPaymentsQuery, LndError,
+    LndPaymentList, LndPaymentRequest, LndPaymentRequestBuilder, LndPaymentResponse, NodePubkey,
+    PaymentHash, PaymentOutcome, Preimage, KEYSEND_RECORD_TYPE,
+};
+
+const DEFAULT_PAYMENT_TIMEOUT: Duration = Duration::from_secs(60);
+/// Floor of the default fee limit, as base fees dominate on small payments.
+const MIN_DEFAULT_FEE_LIMIT_MSAT: u64 = 10_000;
+
+type ProgressCallback = Arc<dyn Fn(&LndPaymentResponse) + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FeeLimit {
+    Sat(u64),
+    Msat(u64),
+}
+
+/// Fee limit used unless one is set: 1% of the amount, and at least 10 sat.
+fn default_fee_limit_msat(amount_msat: u64) -> u64 {
+    (amount_msat / 100).max(MIN_DEFAULT_FEE_LIMIT_MSAT)
+}
+
+/// Options for [`LightningClient::pay_invoice`].
+#[derive(Clone)]
+pub struct PayOptions {
+    timeout: Duration,
+    fee_limit: Option<FeeLimit>,
+    amount_msat: u64,
+    allow_self_payment: bool,
+    progress: Option<ProgressCallback>,
+}
+impl Default for PayOptions {
+    fn default() -> Self {
+        Self {
+            timeout: DEFAULT_PAYMENT_TIMEOUT,
+            fee_limit: None,
+            amount_msat: 0,
+            allow_self_payment: false,
+            progress: None,
+        }
+    }
+}
+impl Debug for PayOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PayOptions")
+            .field("timeout", &self.timeout)
+            .field("fee_limit", &self.fee_limit)
+            .field("amount_msat", &self.amount_msat)
+            .field("allow_self_payment", &self.allow_self_payment)
+            .field("progress", &self.progress.is_some())
+            .finish()
+    }
+}
+impl PayOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// How long the router keeps trying to find a route, one minute by default.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+    /// Most routing fee to pay in satoshis, replacing any limit set before. Unless
+    /// a limit is set, up to 1% of the amount is paid, and at least 10 sat.
+    pub fn fee_limit_sat(mut self, fee_limit_sat: u64) -> Self {
+        self.fee_limit = Some(FeeLimit::Sat(fee_limit_sat));
+        self
+    }
+    /// Most routing fee to pay in millisatoshis, replacing any limit set before.
+    pub fn fee_limit_msat(mut self, fee_limit_msat: u64) -> Self {
+        self.fee_limit = Some(FeeLimit::Msat(fee_limit_msat));
+        self
+    }
+    /// Amount to pay zero amount invoices with.
+    pub fn amount_msat(mut self, amount_msat: u64) -> Self {
+        self.amount_msat = amount_msat;
+        self
+    }
+    pub fn allow_self_payment(mut self, allow_self_payment: bool) -> Self {
+        self.allow_self_payment = allow_self_payment;
+        self
+    }
+    /// Called with every update the router sends before the payment is final.
+    pub fn on_progress<F>(mut self, progress: F) -> Self
+    where
+        F: Fn(&LndPaymentResponse) + Send + Sync + 'static,
+    {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+    fn apply(
+        &self,
+        builder: LndPaymentRequestBuilder,
+        amount_msat: u64,
+    ) -> LndPaymentRequestBuilder {
+        let builder = builder
+            .timeout_seconds(self.timeout.as_secs().clamp(1, u32::MAX as u64) as u32)
+            .allow_self_payment(self.allow_self_payment);
+        match self.fee_limit {
+            Some(FeeLimit::Sat(sat)) => builder.fee_limit_sat(sat),
+            Some(FeeLimit::Msat(msat)) => builder.fee_limit_msat(msat),
+            None => builder.fee_limit_msat(default_fee_limit_msat(amount_msat)),
+        }
+    }
+    fn request(&self, payment_request: String) -> BrightResult<LndPaymentRequest> {
+        let amount_msat = payment_request
+            .parse::<DecodedInvoice>()
+            .ok()
+            .and_then(|invoice| invoice.amount_msat())
+            .unwrap_or(self.amount_msat);
+        self.apply(LndPaymentRequest::builder(payment_request), amount_msat)
+            .amt_msat(self.amount_msat)
+            .build()
+    }
+}
+
+enum PaymentStream {
+    Send(LightningClient, Box<BrightResult<LndPaymentRequest>>),
+    /// Tracks the payments on `path`, `until_final` ending the stream after the
+    /// first final update.
+    Track {
+        client: LightningClient,
+        path: String,
+        until_final: bool,
+    },
+    Reading(LndWebsocket, bool),
+    Done,
+}
+impl PaymentStream {
+    async fn send(
+        client: LightningClient,
+        request: Box<BrightResult<LndPaymentRequest>>,
+    ) -> BrightResult<LndWebsocket> {
+        let request = (*request)?;
+        let websocket = client.invoice_channel().await?;
+        websocket.sender.send(request).await?;
+        Ok(websocket)
+    }
+    async fn next(self) -> Option<(BrightResult<LndPaymentResponse>, Self)> {
+        let connected = match self {
+            PaymentStream::Done => return None,
+            PaymentStream::Reading(websocket, until_final) => Ok((websocket, until_final)),
+            PaymentStream::Send(client, request) => {
+                Self::send(client, request).await.map(|ws| (ws, true))
+            }
+            PaymentStream::Track {
+                client,
+                path,
+                until_final,
+            } => client.websocket(&path).await.map(|ws| (ws, until_final)),
+        };
+        let (websocket, until_final) = match connected {
+            Ok(connected) => connected,
+            Err(e) => return Some((Err(e), PaymentStream::Done)),
+        };
+        loop {
+            match websocket.receiver.read::<LndPaymentResponse>().await {
+                Some(LndWebsocketMessage::Response(update)) if until_final && update.is_final() => {
+                    return Some((Ok(update), PaymentStream::Done));
+                }
+                Some(LndWebsocketMessage::Response(update)) => {
+                    return Some((Ok(update), PaymentStream::Reading(websocket, until_final)));
+                }
+                Some(LndWebsocketMessage::Ping) => continue,
+                Some(LndWebsocketMessage::Error(e)) => {
+                    return Some((Err(BrightError::from(e)), PaymentStream::Done));
+                }
+                None => {
+                    let closed = LndError::new(
+                        GrpcStatus::Unavailable,
+                        "Router stream closed before the payment completed".to_string(),
+                    );
+                    return Some((Err(BrightError::from(closed)), PaymentStream::Done));
+                }
+            }
+        }
+    }
+}
+
+async fn outcome<S>(updates: S, progress: Option<&ProgressCallback>) -> BrightResult<PaymentOutcome>
+where
+    S: Stream<Item = BrightResult<LndPaymentResponse>>,
+{
+    let mut updates = std::pin::pin!(updates);
+    while let Some(update) = updates.next().await {
+        let update = update?;
+        if update.is_final() {
+            return PaymentOutcome::try_from(update);
+        }
+        if let Some(progress) = progress {
+            progress(&update);
+        }
+    }
+    Err(BrightError::from(LndError::new(
+        GrpcStatus::Unavailable,
+        "Router stream closed before the payment completed".to_string(),
+    )))
+}
+
+impl LightningClient {
+    /// Sends a payment through `/v2/router/send` and streams its status updates.
+    ///
+    /// The stream ends after the update where the payment succeeded or failed, or
+    /// after an error.
+    pub fn send_payment_stream(
+        &self,
+        request: LndPaymentRequest,
+    ) -> impl Stream<Item = BrightResult<LndPaymentResponse>> + Send + 'static {
+        stream::unfold(
+            PaymentStream::Send(self.clone(), Box::new(Ok(request))),
+            PaymentStream::next,
+        )
+    }
+    /// Sends a payment, resolving once the router has settled on an outcome.
+    pub async fn send_payment(&self, request: LndPaymentRequest) -> BrightResult<PaymentOutcome> {
+        outcome(self.send_payment_stream(request), None).await
+    }
+    /// Pays a BOLT11 invoice and streams the router's status updates, see
+    /// [`Self::send_payment_stream`].
+    pub fn pay_invoice_stream(
+        &self,
+        payment_request: impl Into<String>,
+        options: &PayOptions,
+    ) -> impl Stream<Item = BrightResult<LndPaymentResponse>> + Send + 'static {
+        let request = options.request(payment_request.into());
+        stream::unfold(
+            PaymentStream::Send(self.clone(), Box::new(request)),
+            PaymentStream::next,
+        )
+    }
+    /// Pays a BOLT11 invoice, resolving once the router has settled on an outcome.
+    ///
+    /// A payment the router gave up on is a [`PaymentOutcome::Failed`], errors are
+    /// reserved for invalid requests and connection failures.
+    pub async fn pay_invoice(
+        &self,
+        payment_request: impl Into<String>,
+        options: &PayOptions,
+    ) -> BrightResult<PaymentOutcome> {
+        let updates = self.pay_invoice_stream(payment_request, options);
+        outcome(updates, options.progress.as_ref()).await
+    }
+    /// Pays `amount_msat` to the node `dest_pubkey` without an invoice.
+    ///
+    /// A random preimage is sent to the destination in the keysend record, along
+    /// with `custom_records` such as podcast boostagrams.
+    pub async fn keysend(
+        &self,
+        dest_pubkey: &str,
+        amount_msat: u64,
+        custom_records: impl IntoIterator<Item = (u64, Vec<u8>)>,
+    ) -> BrightResult<PaymentOutcome> {
+        self.keysend_with_options(dest_pubkey, amount_msat, custom_records, &PayOptions::new())
+            .await
+    }
+    /// [`Self::keysend`] with a timeout, fee limit and progress callback. The
+    /// amount of `options` is ignored.
+    pub async fn keysend_with_options(
+        &self,
+        dest_pubkey: &str,
+        amount_msat: u64,
+        custom_records: impl IntoIterator<Item = (u64, Vec<u8>)>,
+        options: &PayOptions,
+    ) -> BrightResult<PaymentOutcome> {
+        let dest = dest_pubkey.parse::<NodePubkey>()?;
+        let mut builder = options
+            .apply(
+                LndPaymentRequest::keysend(dest, Preimage::random()?),
+                amount_msat,
+            )
+            .amt_msat(amount_msat);
+        for (record_type, value) in custom_records {
+            if record_type == KEYSEND_RECORD_TYPE {
+                return Err(BrightError::invalid_input(
+                    "The keysend record is set from the generated preimage",
+                ));
+            }
+            builder = builder.dest_custom_record(record_type, value);
+        }
+        let updates = self.send_payment_stream(builder.build()?);
+        outcome(updates, options.progress.as_ref()).await
+    }
+    /// Streams the state of a payment sent earlier from `/v2/router/track/{hash}`,
+    /// starting with its current state and ending once it is final.
+    ///
+    /// Used to find out what happened to payments after a restart.
+    pub fn track_payment(
+        &self,
+        payment_hash: &PaymentHash,
+    ) -> impl Stream<Item = BrightResult<LndPaymentResponse>> + Send + 'static {
+        let track = PaymentStream::Track {
+            client: self.clone(),
+            path: format!(
+                "/v2/router/track/{}?no_inflight_updates=false",
+                payment_hash.to_base64_url()
+            ),
+            until_final: true,
+        };
+        stream::unfold(track, PaymentStream::next)
+    }
+    /// Waits for the outcome of a payment sent earlier, see [`Self::track_payment`].
+    pub async fn payment_outcome(
+        &self,
+        payment_hash: &PaymentHash,
+    ) -> BrightResult<PaymentOutcome> {
+        outcome(self.track_payment(payment_hash), None).await
+    }
+    /// Streams the updates of every payment the node sends from
+    /// `/v2/router/payments`, without replaying earlier ones. The stream ends after
+    /// an error.
+    pub fn track_all_payments(
+        &self,
+    ) -> impl Stream<Item = BrightResult<LndPaymentResponse>> + Send + 'static {
+        let track = PaymentStream::Track {
+            client: self.clone(),
+            path: "/v2/router/payments".to_string(),
+            until_final: false,
+        };
+        stream::unfold(track, PaymentStream::next)
+    }
+    /// Lists a page of payments from `/v1/payments`.
+    pub async fn list_payments(&self, query: &ListPaymentsQuery) -> BrightResult<LndPaymentList> {
+        let url = format!("https://{}/v1/payments", self.url());
+        let response = self.client.get(&url).query(query).send().await?;
+        Self::handle_response(response).await
+    }
+    /// Streams every payment matching `query`, fetching the next page as the previous
+    /// one is consumed. The stream ends after the first error.
+    pub fn list_payments_stream(
+        &self,
+        query: ListPaymentsQuery,
+    ) -> impl Stream<Item = BrightResult<LndPaymentResponse>> + Send + 'static {
+        let client = self.clone();
+        stream::try_unfold(Some(query), move |query| {
+            let client = client.clone();
+            async move {
+                let Some(query) = query else {
+                    return Ok::<_, BrightError>(None);
+                };
+                let page = client.list_payments(&query).await?;
+                if page.payments.is_empty() {
+                    return Ok(None);
+                }
+                let next = query.next_page(&page);
+                let next = (next != query).then_some(next);
+                Ok(Some((page.payments, next)))
+            }
+        })
+        .map_ok(|payments| stream::iter(payments.into_iter().map(Ok)))
+        .try_flatten()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use futures_util::{StreamExt, TryStreamExt};
+
+    use super::PayOptions;
+    use crate::{
+        testing::{MockLnd, MockPaymentUpdate},
+        InvoicePaymentState, ListPaymentsQuery, LndFailureCode, LndHtlcStatus,
+        LndPaymentFailureReason, PaymentHash, PaymentOutcome, KEYSEND_RECORD_TYPE,
+    };
+
+    #[tokio::test]
+    async fn pays_invoice_with_progress() -> anyhow::Result<()> {
+        let lnd = MockLnd::start().await;
+        let client = lnd.client();
+        let pay_request = lnd.remote_invoice(50_000, "Coffee");
+        let progress = Arc::new(Mutex::new(vec![]));
+        let seen = progress.clone();
+        let options = PayOptions::new()
+            .fee_limit_sat(10)
+            .on_progress(move |update| seen.lock().unwrap().push(update.status()));
+        let PaymentOutcome::Succeeded(success) = client.pay_invoice(&pay_request, &options).await?
+        else {
+            panic!("Payment failed");
+        };
+        assert_eq!(success.value_msat, 50_000);
+        assert_eq!(success.fee_msat, 1_050);
+        assert_eq!(success.preimage.payment_hash(), success.payment_hash);
+        assert_eq!(success.htlcs.len(), 1);
+        assert_eq!(success.htlcs[0].status, LndHtlcStatus::Succeeded);
+        assert_eq!(success.htlcs[0].preimage, Some(success.preimage));
+        let route = &success.htlcs[0].route;
+        assert_eq!(route.total_fees_msat, success.fee_msat);
+        assert_eq!(route.total_amt_msat, 51_050);
+        assert_eq!(route.hops.len(), 2);
+        let mpp = route.hops[1].mpp_record.as_ref().unwrap();
+        assert_eq!(mpp.total_amt_msat, 50_000);
+        assert_eq!(
+            *progress.lock().unwrap(),
+            vec![InvoicePaymentState::InFlight]
+        );
+
+        let amountless = lnd.remote_invoice(0, "Tip");
+        assert!(client
+            .pay_invoice(&amountless, &PayOptions::new())
+            .await
+            .is_err());
+        let outcome = client
+            .pay_invoice(&amountless, &PayOptions::new().amount_msat(3_000))
+            .await?;
+        assert!(outcome.is_success());
+        Ok(())
+    }
+    #[tokio::test]
+    async fn applies_fee_limits() -> anyhow::Result<()> {
+        let lnd = MockLnd::start().await;
+        let client = lnd.client();
+        // The mock charges 1 sat plus 1000 ppm, 1_050 msat on 50 sat.
+        let pay_request = lnd.remote_invoice(50_000, "Fees");
+        let outcome = client
+            .pay_invoice(&pay_request, &PayOptions::new().fee_limit_sat(0))
+            .await?;
+        let PaymentOutcome::Failed(failure) = outcome else {
+            panic!("Paid without a fee budget");
+        };
+        assert_eq!(failure.reason, LndPaymentFailureReason::NoRoute);
+        let outcome = client
+            .pay_invoice(&pay_request, &PayOptions::new().fee_limit_msat(1_049))
+            .await?;
+        assert!(!outcome.is_success());
+        let outcome = client
+            .pay_invoice(
+                &pay_request,
+                &PayOptions::new().fee_limit_sat(0).fee_limit_msat(1_050),
+            )
+            .await?;
+        assert!(outcome.is_success());
+
+        let outcome = client
+            .pay_invoice(&lnd.remote_invoice(50_000, "Default"), &PayOptions::new())
+            .await?;
+        assert!(outcome.is_success());
+        Ok(())
+    }
+    #[tokio::test]
+    async fn reports_typed_failure() -> anyhow::Result<()> {
+        let lnd = MockLnd::start().await;
+        let client = lnd.client();
+        let pay_request = lnd.remote_invoice(5_000, "Scripted");
+        lnd.script_payment(
+            &pay_request,
+            vec![
+                MockPaymentUpdate::Initiated,
+                MockPaymentUpdate::InFlight,
+                MockPaymentUpdate::Failed("FAILURE_REASON_NO_ROUTE".to_string()),
+            ],
+        );
+        let updates = client
+            .pay_invoice_stream(&pay_request, &PayOptions::new())
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(updates.len(), 3);
+
+        lnd.script_payment(
+            &pay_request,
+            vec![MockPaymentUpdate::Failed(
+                "FAILURE_REASON_NO_ROUTE".to_string(),
+            )],
+        );
+        let outcome = client.pay_invoice(&pay_request, &PayOptions::new()).await?;
+        let PaymentOutcome::Failed(failure) = &outcome else {
+            panic!("Payment succeeded");
+        };
+        assert_eq!(failure.reason, LndPaymentFailureReason::NoRoute);
+        let htlc = &failure.htlcs[0];
+        assert_eq!(htlc.status, LndHtlcStatus::Failed);
+        assert_eq!(
+            htlc.failure.as_ref().unwrap().code,
+            LndFailureCode::TemporaryChannelFailure
+        );
+        assert_eq!(htlc.failure_source(), htlc.route.hops.first());
+        assert!(outcome.preimage().is_none());
+        assert!(client
+            .pay_invoice("lnbc1", &PayOptions::new())
+            .await
+            .is_err());
+        Ok(())
+    }
+    #[tokio::test]
+    async fn keysends_to_node() -> anyhow::Result<()> {
+        let lnd = MockLnd::start().await;
+        let client = lnd.client();
+        let dest = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+        let boost = (7_629_169, br#"{"action":"boost"}"#.to_vec());
+        let outcome = client
+            .keysend_with_options(
+                dest,
+                10_000,
+                [boost.clone()],
+                &PayOptions::new().fee_limit_sat(5),
+            )
+            .await?;
+        let PaymentOutcome::Succeeded(success) = outcome else {
+            panic!("Keysend failed");
+        };
+        assert!(success.preimage.matches(&success.payment_hash));
+        assert_eq!(success.value_msat, 10_000);
+        assert_eq!(success.fee_msat, 1_010);
+        let final_hop = success.htlcs[0].route.hops.last().unwrap();
+        assert_eq!(final_hop.pub_key, dest);
+        assert!(final_hop.mpp_record.is_none());
+        assert_eq!(final_hop.custom_records[&boost.0], boost.1);
+        assert_eq!(
+            final_hop.custom_records[&KEYSEND_RECORD_TYPE],
+            success.preimage.as_bytes().to_vec()
+        );
+
+        assert!(client.keysend("02abcd", 1_000, []).await.is_err());
+        assert!(client.keysend(dest, 0, []).await.is_err());
+        assert!(client
+            .keysend(dest, 1_000, [(KEYSEND_RECORD_TYPE, vec![0; 32])])
+            .await
+            .is_err());
+        Ok(())
+    }
+    #[tokio::test]
+    async fn tracks_payment_after_restart() -> anyhow::Result<()> {
+        let lnd = MockLnd::start().await;
+        let client = lnd.client();
+        let pay_request = lnd.remote_invoice(8_000, "Payout");
+        lnd.script_payment(&pay_request, vec![MockPaymentUpdate::InFlight]);
+        let in_flight = Box::pin(client.pay_invoice_stream(&pay_request, &PayOptions::new()))
+            .next()
+            .await
+            .unwrap()?;
+        let hash = in_flight.payment_hash();
+
+        let mut tracked = Box::pin(client.track_payment(&hash));
+        let current = tracked.next().await.unwrap()?;
+        assert_eq!(current.status(), InvoicePaymentState::InFlight);
+        assert_eq!(current.payment_index(), in_flight.payment_index());
+        lnd.resolve_payment(&hash, MockPaymentUpdate::Succeeded)?;
+        let settled = tracked.next().await.unwrap()?;
+        assert_eq!(settled.status(), InvoicePaymentState::Succeeded);
+        assert!(tracked.next().await.is_none());
+
+        let outcome = client.payment_outcome(&hash).await?;
+        assert!(outcome.preimage().unwrap().matches(&hash));
+        assert!(
+            Box::pin(client.track_payment(&PaymentHash::from_bytes([0x09; 32])))
+                .next()
+                .await
+                .unwrap()
+                .is_err()
+        );
+        Ok(())
+    }
+    #[tokio::test]
+    async fn tracks_and_lists_all_payments() -> anyhow::Result<()> {
+        let lnd = MockLnd::start().await;
+        let client = lnd.client();
+        let tracker = tokio::spawn(client.track_all_payments().take(4).try_collect::<Vec<_>>());
+        lnd.payment_tracker_connected().await;
+
+        let failed = lnd.remote_invoice(1_000, "Failed");
+        lnd.script_payment(
+            &failed,
+            vec![MockPaymentUpdate::Failed(
+                "FAILURE_REASON_NO_ROUTE".to_string(),
+            )],
+        );
+        let mut hashes = vec![];
+        for pay_request in [lnd.remote_invoice(2_000, "First"), failed] {
+            let outcome = client.pay_invoice(&pay_request, &PayOptions::new()).await?;
+            hashes.push(outcome.payment_hash());
+        }
+        for amount_msat in [3_000, 4_000] {
+            let pay_request = lnd.remote_invoice(amount_msat, "More");
+            let outcome = client.pay_invoice(&pay_request, &PayOptions::new()).await?;
+            hashes.push(outcome.payment_hash());
+        }
+        let tracked = tracker.await??;
+        assert_eq!(tracked[0].status(), InvoicePaymentState::InFlight);
+        assert_eq!(tracked[1].status(), InvoicePaymentState::Succeeded);
+        // The scripted failure skips the in-flight update.
+        assert_eq!(tracked[2].payment_hash(), hashes[1]);
+        assert_eq!(tracked[2].status(), InvoicePaymentState::Failed);
+        assert_eq!(tracked[3].payment_hash(), hashes[2]);
+
+        let page = client
+            .list_payments(&ListPaymentsQuery::new().count_total_payments(true))
+            .await?;
+        assert_eq!(page.total_num_payments, 3);
+        assert!(page
+            .payments
+            .iter()
+            .all(|payment| payment.status() == InvoicePaymentState::Succeeded));
+
+        let all = client
+            .list_payments_stream(
+                ListPaymentsQuery::new()
+                    .include_incomplete(true)
+                    .max_payments(1),
+            )
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(
+            all.iter()
+                .map(|payment| payment.payment_hash())
+                .collect::<Vec<_>>(),
+            hashes
+        );
+        assert_eq!(all[1].failure_reason(), LndPaymentFailureReason::NoRoute);
+        let newest = client
+            .list_payments(&ListPaymentsQuery::new().reversed(true).max_payments(1))
+            .await?;
+        assert_eq!(newest.payments[0].payment_hash(), hashes[3]);
+        Ok(())
+    }
+}
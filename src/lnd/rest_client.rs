@@ -1,180 +1,369 @@
-use base64::prelude::*;
-use reqwest::header::{HeaderMap, HeaderValue};
-use std::io::Read;
+use std::sync::Arc;
+
+use futures_util::{stream, Stream, TryStreamExt};
+use serde::de::DeserializeOwned;
 
 use crate::{
-    lnd::{LndHodlInvoice, LndHodlInvoiceState, LndInfo, LndInvoice, LndInvoiceRequestBody},
-    LndInvoiceList, LndWebsocket,
+    lnd::{
+        LndHodlInvoice, LndHodlInvoiceRequestBody, LndHodlInvoiceState, LndInfo, LndInvoice,
+        LndInvoiceRequestBody,
+    },
+    BrightError, BrightResult, InvoiceLookup, InvoiceStore, ListInvoicesQuery, LndError,
+    LndInvoiceList, LndInvoiceState, LndPayReq, LndWebsocket, PaymentHash, Preimage, StoredInvoice,
 };
 
 use super::{
-    LndAddressProperty, LndListAddressesResponse, LndNewAddress, LndNextAddressRequest,
-    LndPaymentInvoice, OnchainAddressType,
+    store::unix_now, LightningClientBuilder, LndAddressProperty, LndChannelBalance, LndConnectUri,
+    LndListAddressesResponse, LndNewAddress, LndNextAddressRequest, LndPaymentInvoice,
+    LndTlsConfig, LndWalletBalance, OnchainAddressType,
 };
 
 #[derive(Clone)]
 pub struct LightningClient {
-    url: &'static str,
-    data_dir: &'static str,
+    url: String,
+    macaroon: String,
+    tls: LndTlsConfig,
     pub client: reqwest::Client,
+    store: Option<Arc<dyn InvoiceStore>>,
 }
 
 impl LightningClient {
-    pub async fn dud_server() -> anyhow::Result<Self> {
-        let client = reqwest::Client::builder()
-            .danger_accept_invalid_certs(true)
-            .build()?;
-        Ok(Self {
-            url: "localhost:10009",
-            client,
-            data_dir: "",
-        })
+    pub fn builder() -> LightningClientBuilder {
+        LightningClientBuilder::new()
     }
-    pub async fn new(url: &'static str, data_dir: &'static str) -> anyhow::Result<Self> {
-        let mut default_header = HeaderMap::new();
-        let macaroon = Self::macaroon(data_dir)?;
-        let mut header_value = HeaderValue::from_str(&macaroon).unwrap();
-        header_value.set_sensitive(true);
-        default_header.insert("Grpc-Metadata-macaroon", header_value);
-        default_header.insert("Accept", HeaderValue::from_static("application/json"));
-        default_header.insert("Content-Type", HeaderValue::from_static("application/json"));
-        let client = reqwest::Client::builder()
-            .danger_accept_invalid_certs(true)
-            .default_headers(default_header)
-            .build()?;
-        Ok(Self {
+    pub async fn dud_server() -> BrightResult<Self> {
+        LightningClientBuilder::new()
+            .host("localhost:10009")
+            .build()
+    }
+    pub async fn new(url: &str, data_dir: &str) -> BrightResult<Self> {
+        LightningClientBuilder::new()
+            .host(url)
+            .macaroon_path(data_dir)
+            .build()
+    }
+    pub fn from_lndconnect(uri: &str) -> BrightResult<Self> {
+        let uri: LndConnectUri = uri.parse()?;
+        LightningClientBuilder::new().lnd_connect(&uri).build()
+    }
+    pub(crate) fn from_parts(
+        url: String,
+        macaroon: String,
+        tls: LndTlsConfig,
+        client: reqwest::Client,
+        store: Option<Arc<dyn InvoiceStore>>,
+    ) -> Self {
+        Self {
             url,
+            macaroon,
+            tls,
             client,
-            data_dir,
-        })
+            store,
+        }
     }
-    fn macaroon(data_dir: &'static str) -> anyhow::Result<String> {
-        let mut macaroon = vec![];
-        let mut file = std::fs::File::open(data_dir)?;
-        file.read_to_end(&mut macaroon)?;
-        Ok(macaroon.iter().map(|b| format!("{:02x}", b)).collect())
+    /// Writes invoices created, settled or canceled through this client to `store`.
+    pub fn with_invoice_store(mut self, store: Arc<dyn InvoiceStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+    pub fn invoice_store(&self) -> Option<&Arc<dyn InvoiceStore>> {
+        self.store.as_ref()
+    }
+    fn record_invoice(&self, invoice: StoredInvoice) -> BrightResult<()> {
+        match &self.store {
+            Some(store) => store.save(invoice),
+            None => Ok(()),
+        }
+    }
+    pub(crate) fn record_state(
+        &self,
+        payment_hash: &PaymentHash,
+        state: LndInvoiceState,
+    ) -> BrightResult<()> {
+        match &self.store {
+            Some(store) => store.update_state(payment_hash, state),
+            None => Ok(()),
+        }
+    }
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+    pub(crate) fn macaroon(&self) -> &str {
+        &self.macaroon
+    }
+    /// Checks the HTTP status of an LND REST response and decodes its body, turning
+    /// error bodies into [`LndError`]s.
+    pub(crate) async fn handle_response<T>(response: reqwest::Response) -> BrightResult<T>
+    where
+        T: DeserializeOwned,
+    {
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            let error = LndError::from_response(status.as_u16(), &body);
+            tracing::debug!("LND error {}: {}", status, error.message());
+            return Err(error.into());
+        }
+        Ok(serde_json::from_str(&body)?)
     }
-    pub async fn get_info(&self) -> anyhow::Result<LndInfo> {
+    pub async fn get_info(&self) -> BrightResult<LndInfo> {
         let url = format!("https://{}/v1/getinfo", self.url);
         let response = self.client.get(&url).send().await?;
-        let response = response.text().await?;
-        LndInfo::try_from(response)
+        Self::handle_response(response).await
     }
-    pub async fn channel_balance(&self) -> anyhow::Result<()> {
+    pub async fn channel_balance(&self) -> BrightResult<LndChannelBalance> {
         let url = format!("https://{}/v1/balance/channels", self.url);
         let response = self.client.get(&url).send().await?;
-        let _response = response.text().await?;
-        Ok(())
+        Self::handle_response(response).await
+    }
+    pub async fn wallet_balance(&self) -> BrightResult<LndWalletBalance> {
+        let url = format!("https://{}/v1/balance/blockchain", self.url);
+        let response = self.client.get(&url).send().await?;
+        Self::handle_response(response).await
     }
     pub async fn get_invoice(
         &self,
         form: LndInvoiceRequestBody,
-    ) -> anyhow::Result<LndPaymentInvoice> {
+    ) -> BrightResult<LndPaymentInvoice> {
+        let amount_msat = form.amount_msat()?;
         let url = format!("https://{}/v1/invoices", self.url);
         let response = self.client.post(&url).body(form.to_string());
         let response = response.send().await?;
-        let response = response.json::<LndPaymentInvoice>().await?;
-        Ok(response)
+        let invoice: LndPaymentInvoice = Self::handle_response(response).await?;
+        self.record_invoice(StoredInvoice {
+            preimage: form.r_preimage(),
+            ..StoredInvoice::new(invoice.r_hash, invoice.payment_request.clone(), amount_msat)
+        })?;
+        Ok(invoice)
     }
-    pub async fn list_invoices(&self) -> anyhow::Result<Vec<LndInvoice>> {
-        let url = format!("https://{}/v1/invoices", self.url);
-        let response = self.client.get(&url).send().await?;
-        let response = response.json::<LndInvoiceList>().await?;
+    pub async fn list_invoices(&self) -> BrightResult<Vec<LndInvoice>> {
+        let response = self
+            .list_invoices_page(&ListInvoicesQuery::default())
+            .await?;
         Ok(response.invoices)
     }
+    pub async fn list_invoices_page(
+        &self,
+        query: &ListInvoicesQuery,
+    ) -> BrightResult<LndInvoiceList> {
+        let url = format!("https://{}/v1/invoices", self.url);
+        let response = self.client.get(&url).query(query).send().await?;
+        Self::handle_response(response).await
+    }
+    /// Streams every invoice matching `query`, fetching the next page as the previous
+    /// one is consumed. The stream ends after the first error.
+    pub fn list_invoices_stream(
+        &self,
+        query: ListInvoicesQuery,
+    ) -> impl Stream<Item = BrightResult<LndInvoice>> + Send + 'static {
+        let client = self.clone();
+        stream::try_unfold(Some(query), move |query| {
+            let client = client.clone();
+            async move {
+                let Some(query) = query else {
+                    return Ok::<_, BrightError>(None);
+                };
+                let page = client.list_invoices_page(&query).await?;
+                if page.invoices.is_empty() {
+                    return Ok(None);
+                }
+                let next = query.next_page(&page);
+                let next = (next != query).then_some(next);
+                Ok(Some((page.invoices, next)))
+            }
+        })
+        .map_ok(|invoices| stream::iter(invoices.into_iter().map(Ok)))
+        .try_flatten()
+    }
     pub async fn new_onchain_address(
         &self,
         request: LndNextAddressRequest,
-    ) -> anyhow::Result<LndNewAddress> {
+    ) -> BrightResult<LndNewAddress> {
         let url = format!("https://{}/v2/wallet/address/next", self.url);
         let request_str: String = request.into();
         let response = self.client.post(&url).body(request_str).send().await?;
-        tracing::info!("{:?}", response);
-        let response = response.json::<LndNewAddress>().await?;
-        Ok(response)
+        Self::handle_response(response).await
     }
     pub async fn list_onchain_addresses(
         &self,
         account: &str,
         address_type: OnchainAddressType,
-    ) -> anyhow::Result<Vec<LndAddressProperty>> {
+    ) -> BrightResult<Vec<LndAddressProperty>> {
         let url = format!("https://{}/v2/wallet/addresses", self.url);
         let response = self.client.get(&url).send().await?;
-        let response = response
-            .json::<LndListAddressesResponse>()
+        let response = Self::handle_response::<LndListAddressesResponse>(response)
             .await?
             .find_addresses(account, address_type);
         Ok(response)
     }
-    pub async fn invoice_channel(&self) -> anyhow::Result<LndWebsocket> {
-        let url = format!("wss://{}/v2/router/send?method=POST", self.url);
-        let lnd_ws = LndWebsocket::new()
-            .connect(self.url.to_string(), Self::macaroon(self.data_dir)?, url)
-            .await?;
-        Ok(lnd_ws)
+    /// Opens a websocket to a streaming endpoint, `path` including any query.
+    pub(crate) async fn websocket(&self, path: &str) -> BrightResult<LndWebsocket> {
+        let url = format!("wss://{}{}", self.url, path);
+        LndWebsocket::new()
+            .connect(
+                self.url.clone(),
+                self.macaroon().to_string(),
+                url,
+                &self.tls,
+            )
+            .await
+    }
+    pub async fn invoice_channel(&self) -> BrightResult<LndWebsocket> {
+        self.websocket("/v2/router/send?method=POST").await
     }
     pub async fn lookup_invoice(
         &self,
-        r_hash_url_safe: String,
-    ) -> anyhow::Result<LndHodlInvoiceState> {
+        payment_hash: &PaymentHash,
+    ) -> BrightResult<LndHodlInvoiceState> {
         let query = format!(
             "https://{}/v2/invoices/lookup?payment_hash={}",
-            self.url, r_hash_url_safe
+            self.url,
+            payment_hash.to_base64_url()
         );
         let response = self.client.get(&query).send().await?;
-        let response = response.json::<LndHodlInvoiceState>().await?;
-        Ok(response)
+        Self::handle_response(response).await
     }
-    pub async fn subscribe_to_invoice(
+    /// Looks up any invoice by payment hash on `/v1/invoice/{r_hash_str}`.
+    pub async fn lookup_invoice_by_hash(
         &self,
-        r_hash_url_safe: String,
-    ) -> anyhow::Result<LndWebsocket> {
-        let query = format!(
-            "wss://{}/v2/invoices/subscribe/{}",
-            self.url, r_hash_url_safe
-        );
-        let lnd_ws = LndWebsocket::new()
-            .connect(self.url.to_string(), Self::macaroon(self.data_dir)?, query)
+        payment_hash: &PaymentHash,
+    ) -> BrightResult<LndInvoice> {
+        let url = format!("https://{}/v1/invoice/{}", self.url, payment_hash.to_hex());
+        let response = self.client.get(&url).send().await?;
+        Self::handle_response(response).await
+    }
+    /// Decodes a payment request on LND's side, to cross-check a [`DecodedInvoice`].
+    ///
+    /// [`DecodedInvoice`]: crate::DecodedInvoice
+    pub async fn decode_pay_req(&self, pay_req: &str) -> BrightResult<LndPayReq> {
+        let url = format!("https://{}/v1/payreq/{}", self.url, pay_req.trim());
+        let response = self.client.get(&url).send().await?;
+        Self::handle_response(response).await
+    }
+    /// Looks up any invoice on `/v2/invoices/lookup`.
+    pub async fn lookup_invoice_by(&self, lookup: InvoiceLookup) -> BrightResult<LndInvoice> {
+        let url = format!("https://{}/v2/invoices/lookup", self.url);
+        let response = self
+            .client
+            .get(&url)
+            .query(&[lookup.query()])
+            .send()
             .await?;
-        Ok(lnd_ws)
+        Self::handle_response(response).await
+    }
+    pub async fn subscribe_to_invoice(
+        &self,
+        payment_hash: &PaymentHash,
+    ) -> BrightResult<LndWebsocket> {
+        self.websocket(&format!(
+            "/v2/invoices/subscribe/{}",
+            payment_hash.to_base64_url()
+        ))
+        .await
     }
     pub async fn get_hodl_invoice(
         &self,
-        payment_hash: String,
+        payment_hash: &PaymentHash,
         amount: u64,
-    ) -> anyhow::Result<LndHodlInvoice> {
+    ) -> BrightResult<LndHodlInvoice> {
+        self.add_hodl_invoice(&LndHodlInvoiceRequestBody::new(*payment_hash, amount))
+            .await
+    }
+    pub async fn add_hodl_invoice(
+        &self,
+        body: &LndHodlInvoiceRequestBody,
+    ) -> BrightResult<LndHodlInvoice> {
+        self.create_hodl_invoice(body, None).await
+    }
+    pub(crate) async fn create_hodl_invoice(
+        &self,
+        body: &LndHodlInvoiceRequestBody,
+        preimage: Option<Preimage>,
+    ) -> BrightResult<LndHodlInvoice> {
+        body.validate()?;
+        // The preimage is stored before LND can accept payments for the hash, so a
+        // failing store never leaves an invoice that cannot be settled. An existing
+        // record may hold the preimage or final state of an earlier invoice, so it
+        // is never replaced.
+        if let Some(store) = &self.store {
+            if store.get(&body.hash())?.is_some() {
+                return Err(BrightError::invalid_input(format!(
+                    "Invoice {} is already stored",
+                    body.hash()
+                )));
+            }
+        }
+        let mut stored = StoredInvoice {
+            preimage,
+            hodl: true,
+            ..StoredInvoice::new(body.hash(), String::new(), body.amount_msat()?)
+        };
+        self.record_invoice(stored.clone())?;
+        let invoice = match self.post_hodl_invoice(body).await {
+            Ok(invoice) => invoice,
+            Err(error) => {
+                if let Some(store) = &self.store {
+                    if let Err(e) = store.remove(&body.hash()) {
+                        tracing::warn!("Failed to remove stored invoice {}: {}", body.hash(), e);
+                    }
+                }
+                return Err(error);
+            }
+        };
+        stored.payment_request = invoice.payment_request();
+        if let Err(e) = self.record_invoice(stored) {
+            tracing::warn!(
+                "Failed to store payment request of hodl invoice {}: {}",
+                body.hash(),
+                e
+            );
+        }
+        Ok(invoice)
+    }
+    async fn post_hodl_invoice(
+        &self,
+        body: &LndHodlInvoiceRequestBody,
+    ) -> BrightResult<LndHodlInvoice> {
         let url = format!("https://{}/v2/invoices/hodl", self.url);
-
-        let response = self
-            .client
-            .post(&url)
-            .json(&serde_json::json!({ "value": amount, "hash": payment_hash }))
-            .send()
-            .await?;
-        let response = response.text().await?;
-        LndHodlInvoice::try_from(response)
+        let response = self.client.post(&url).json(body).send().await?;
+        Self::handle_response(response).await
     }
-    pub async fn settle_htlc(&self, preimage: String) -> anyhow::Result<()> {
+    pub async fn settle_htlc(&self, preimage: &Preimage) -> BrightResult<()> {
         let url = format!("https://{}/v2/invoices/settle", self.url);
-        let hex_bytes = preimage.chars().collect::<Vec<char>>();
-        let preimage = hex_bytes
-            .chunks(2)
-            .map(|chunk| {
-                let s: String = chunk.iter().collect();
-                u8::from_str_radix(&s, 16).unwrap()
-            })
-            .collect::<Vec<u8>>();
-        let preimage = BASE64_URL_SAFE.encode(&preimage);
         let response = self
             .client
             .post(&url)
             .json(&serde_json::json!({ "preimage": preimage }))
             .send()
             .await?;
-        let _test = response.text().await?;
+        Self::handle_response::<serde_json::Value>(response).await?;
+        if let Some(store) = &self.store {
+            if let Some(mut invoice) = store.get(&preimage.payment_hash())? {
+                invoice.preimage = Some(*preimage);
+                invoice.state = LndInvoiceState::Settled;
+                invoice.updated_at = unix_now();
+                store.save(invoice)?;
+            }
+        }
         Ok(())
     }
-    pub async fn cancel_htlc(&self, payment_hash: String) -> anyhow::Result<()> {
+    /// Settles a held invoice with the preimage recorded in the invoice store.
+    pub async fn settle_stored(&self, payment_hash: &PaymentHash) -> BrightResult<()> {
+        let preimage = self
+            .store
+            .as_ref()
+            .ok_or_else(|| BrightError::invalid_input("No invoice store configured"))?
+            .get(payment_hash)?
+            .and_then(|invoice| invoice.preimage)
+            .ok_or_else(|| BrightError::invalid_input("No stored preimage for invoice"))?;
+        if !preimage.matches(payment_hash) {
+            return Err(BrightError::invalid_input(
+                "Preimage does not match the payment hash",
+            ));
+        }
+        self.settle_htlc(&preimage).await
+    }
+    pub async fn cancel_htlc(&self, payment_hash: &PaymentHash) -> BrightResult<()> {
         let url = format!("https://{}/v2/invoices/cancel", self.url);
         let response = self
             .client
@@ -182,8 +371,8 @@ impl LightningClient {
             .json(&serde_json::json!({ "payment_hash": payment_hash }))
             .send()
             .await?;
-        response.text().await?;
-        Ok(())
+        Self::handle_response::<serde_json::Value>(response).await?;
+        self.record_state(payment_hash, LndInvoiceState::Canceled)
     }
 }
 
@@ -195,14 +384,255 @@ mod test {
         LndInvoiceRequestBody, LndInvoiceState, LndNextAddressRequest, LndPaymentRequest,
         LndPaymentResponse, LndWebsocketMessage,
     };
+    use futures_util::TryStreamExt;
     use tracing::{error, info};
     use tracing_test::traced_test;
 
     use super::LightningClient;
+    use crate::{
+        testing::{mock_bolt11, MockLnd, MockRequest, MockResponse, MockServer, MOCK_MACAROON},
+        BrightError, BrightResult, GrpcStatus, InvoiceLookup, ListInvoicesQuery,
+        OnchainAddressType, PaymentHash, Preimage,
+    };
+
+    const MOCK_HASH: &str = "3q2-7wAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=";
+
+    fn mock_hash() -> PaymentHash {
+        MOCK_HASH.parse().unwrap()
+    }
+
+    fn mock_lnd(request: MockRequest) -> MockResponse {
+        if request
+            .headers
+            .get("grpc-metadata-macaroon")
+            .map(String::as_str)
+            != Some(MOCK_MACAROON)
+        {
+            return MockResponse::error(401, "");
+        }
+        match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/v1/getinfo") => {
+                MockResponse::ok(r#"{"identity_pubkey":"02aa","block_height":800000}"#)
+            }
+            ("GET", "/v1/balance/channels") => MockResponse::ok(
+                r#"{"balance":"1000","local_balance":{"sat":"1000","msat":"1000000"}}"#,
+            ),
+            ("GET", "/v1/balance/blockchain") => MockResponse::ok(
+                r#"{"total_balance":"2000","confirmed_balance":"2000","account_balance":{"default":{"confirmed_balance":"2000","unconfirmed_balance":"0"}}}"#,
+            ),
+            ("POST", "/v1/invoices") => MockResponse::ok(
+                r#"{"r_hash":"3q2+7wAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=","payment_request":"lnbc1","add_index":"7","payment_addr":"AAAA"}"#,
+            ),
+            ("GET", "/v1/invoices") => MockResponse::ok(
+                r#"{"invoices":[],"last_index_offset":"0","first_index_offset":"0"}"#,
+            ),
+            ("POST", "/v2/wallet/address/next") => MockResponse::ok(r#"{"addr":"bc1p0000"}"#),
+            ("GET", "/v2/wallet/addresses") => MockResponse::ok(
+                r#"{"account_with_addresses":[{"name":"default","address_type":"TAPROOT_PUBKEY","derivation_path":"m/86'/0'/0'","addresses":[{"address":"bc1p0000","is_internal":false,"balance":"0","derivation_path":"m/86'/0'/0'/0/0","public_key":"02aa"}]}]}"#,
+            ),
+            ("GET", "/v2/invoices/lookup")
+                if request.query == format!("payment_hash={}", MOCK_HASH) =>
+            {
+                MockResponse::ok(
+                    r#"{"settled":false,"state":"ACCEPTED","r_hash":"3q2+7wAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=","payment_request":"lnbc1"}"#,
+                )
+            }
+            ("POST", "/v2/invoices/hodl") => MockResponse::json(&serde_json::json!({
+                "payment_addr": "AAAA",
+                "payment_request": mock_bolt11(&mock_hash(), 1_000_000, "Hodl"),
+                "add_index": "8",
+            })),
+            ("POST", "/v2/invoices/settle") if request.body.contains("preimage") => {
+                MockResponse::ok("{}")
+            }
+            ("POST", "/v2/invoices/cancel") if request.body.contains("payment_hash") => {
+                MockResponse::ok("{}")
+            }
+            _ => MockResponse::error(404, "Not Found"),
+        }
+    }
+    async fn call_every_endpoint(client: &LightningClient) -> Vec<BrightResult<()>> {
+        vec![
+            client.get_info().await.map(|_| ()),
+            client.channel_balance().await.map(|_| ()),
+            client.wallet_balance().await.map(|_| ()),
+            client
+                .get_invoice(LndInvoiceRequestBody::new(1000, None))
+                .await
+                .map(|_| ()),
+            client.list_invoices().await.map(|_| ()),
+            client
+                .new_onchain_address(LndNextAddressRequest::default())
+                .await
+                .map(|_| ()),
+            client
+                .list_onchain_addresses("default", OnchainAddressType::TaprootPubkey)
+                .await
+                .map(|_| ()),
+            client.lookup_invoice(&mock_hash()).await.map(|_| ()),
+            client
+                .get_hodl_invoice(&mock_hash(), 1000)
+                .await
+                .map(|_| ()),
+            client.settle_htlc(&Preimage::default()).await,
+            client.cancel_htlc(&mock_hash()).await,
+        ]
+    }
+
+    #[tokio::test]
+    async fn rest_endpoints_decode_responses() -> anyhow::Result<()> {
+        let server = MockServer::start(mock_lnd).await;
+        let client = server.client();
+        for result in call_every_endpoint(&client).await {
+            result?;
+        }
+        let invoice = client
+            .get_invoice(LndInvoiceRequestBody::new(1000, None))
+            .await?;
+        assert_eq!(invoice.add_index, "7");
+        let balance = client.channel_balance().await?;
+        assert_eq!(balance.local_balance.msat, 1_000_000);
+        let addresses = client
+            .list_onchain_addresses("default", OnchainAddressType::TaprootPubkey)
+            .await?;
+        assert_eq!(addresses[0].address, "bc1p0000");
+        let state = client.lookup_invoice(&mock_hash()).await?;
+        assert_eq!(state.state(), HodlState::ACCEPTED);
+        Ok(())
+    }
+    #[tokio::test]
+    async fn rest_endpoints_surface_lnd_errors() -> anyhow::Result<()> {
+        let server = MockServer::start(|_| {
+            MockResponse::error(
+                500,
+                r#"{"code":2,"message":"invoice already settled","details":[]}"#,
+            )
+        })
+        .await;
+        for result in call_every_endpoint(&server.client()).await {
+            match result {
+                Err(BrightError::Lnd(error)) => {
+                    assert_eq!(error.status(), GrpcStatus::Unknown);
+                    assert_eq!(error.message(), "invoice already settled");
+                }
+                other => panic!("Expected LND error, got {:?}", other),
+            }
+        }
+        Ok(())
+    }
+    #[tokio::test]
+    async fn rest_endpoints_reject_bad_macaroon() -> anyhow::Result<()> {
+        let server = MockServer::start(mock_lnd).await;
+        let client = LightningClient::builder()
+            .host("localhost")
+            .port(server.port)
+            .macaroon_hex("00")
+            .tls(server.tls())
+            .build()?;
+        for result in call_every_endpoint(&client).await {
+            assert!(matches!(result, Err(BrightError::Auth(_))));
+        }
+        Ok(())
+    }
+    #[tokio::test]
+    async fn rest_endpoints_reject_bad_input() -> anyhow::Result<()> {
+        assert!(matches!(
+            "zz".parse::<Preimage>(),
+            Err(BrightError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            PaymentHash::try_from(&[0u8; 31][..]),
+            Err(BrightError::InvalidInput(_))
+        ));
+        Ok(())
+    }
+    #[tokio::test]
+    async fn list_invoices_walks_pages() -> anyhow::Result<()> {
+        let lnd = MockLnd::start().await;
+        let client = lnd.client();
+        let mut hashes = vec![];
+        for value in 1..=25 {
+            let invoice = client
+                .get_invoice(LndInvoiceRequestBody::new(value, None))
+                .await?;
+            hashes.push(invoice.r_hash);
+        }
+        for hash in hashes.iter().step_by(2) {
+            lnd.simulate_payment(hash)?;
+        }
+
+        let page = client
+            .list_invoices_page(&ListInvoicesQuery::new().num_max_invoices(10))
+            .await?;
+        assert_eq!(page.invoices.len(), 10);
+        assert_eq!((page.first_index_offset, page.last_index_offset), (1, 10));
+
+        let query = ListInvoicesQuery::new().num_max_invoices(10);
+        let all = client
+            .list_invoices_stream(query)
+            .try_collect::<Vec<_>>()
+            .await?;
+        let all = all.into_iter().map(|i| i.r_hash).collect::<Vec<_>>();
+        assert_eq!(all, hashes);
+
+        let reversed = client
+            .list_invoices_stream(query.reversed(true))
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(reversed.len(), 25);
+        assert_eq!(reversed[0].r_hash, hashes[15]);
+
+        let pending = client
+            .list_invoices_stream(query.pending_only(true))
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(pending.len(), 12);
+        assert!(pending.iter().all(|i| i.state == LndInvoiceState::Open));
+
+        let future = ListInvoicesQuery::new().creation_date_start(u32::MAX as u64);
+        assert!(client
+            .list_invoices_page(&future)
+            .await?
+            .invoices
+            .is_empty());
+        Ok(())
+    }
+    #[tokio::test]
+    async fn lookup_invoice_in_any_encoding() -> anyhow::Result<()> {
+        let lnd = MockLnd::start().await;
+        let client = lnd.client();
+        let created = client
+            .get_invoice(LndInvoiceRequestBody::new(2100, Some("Lookup".to_string())))
+            .await?;
+        let by_hex = client
+            .lookup_invoice_by_hash(&created.r_hash_hex().parse()?)
+            .await?;
+        assert_eq!(by_hex.memo.as_deref(), Some("Lookup"));
+        assert_eq!(by_hex.value, 2100);
+        assert_eq!(by_hex.r_hash, created.r_hash);
+        assert_eq!(
+            client.lookup_invoice_by_hash(&created.r_hash).await?,
+            by_hex
+        );
+
+        let by_hash = client
+            .lookup_invoice_by(InvoiceLookup::payment_hash(created.r_hash_url_safe())?)
+            .await?;
+        let by_addr = client
+            .lookup_invoice_by(InvoiceLookup::payment_addr(&created.payment_addr)?)
+            .await?;
+        assert_eq!(by_hash, by_hex);
+        assert_eq!(by_addr, by_hex);
+
+        let missing = client.lookup_invoice_by_hash(&PaymentHash::default()).await;
+        assert_eq!(missing.unwrap_err().status(), Some(GrpcStatus::NotFound));
+        Ok(())
+    }
     #[tokio::test]
     #[traced_test]
     async fn next_onchain() -> anyhow::Result<()> {
-        let client = LightningClient::new("lnd.illuminodes.com", "./admin.macaroon").await?;
+        let lnd = MockLnd::start().await;
+        let client = lnd.client();
         let invoices = client
             .new_onchain_address(LndNextAddressRequest::default())
             .await?;
@@ -213,7 +643,8 @@ mod test {
     #[tokio::test]
     #[traced_test]
     async fn onchain_list() -> anyhow::Result<()> {
-        let client = LightningClient::new("lnd.illuminodes.com", "./admin.macaroon").await?;
+        let lnd = MockLnd::start().await;
+        let client = lnd.client();
         let invoices = client
             .list_onchain_addresses("default", crate::OnchainAddressType::TaprootPubkey)
             .await?;
@@ -224,7 +655,8 @@ mod test {
     #[tokio::test]
     #[traced_test]
     async fn test_invoice_list() -> anyhow::Result<()> {
-        let client = LightningClient::new("lnd.illuminodes.com", "./admin.macaroon").await?;
+        let lnd = MockLnd::start().await;
+        let client = lnd.client();
         let invoices = client.list_invoices().await?;
         info!("{:?}", invoices);
         Ok(())
@@ -232,18 +664,13 @@ mod test {
     #[tokio::test]
     #[traced_test]
     async fn test_connection() -> anyhow::Result<()> {
-        let client = LightningClient::new("lnd.illuminodes.com", "./admin.macaroon").await?;
+        let lnd = MockLnd::start().await;
+        let client = lnd.client();
         let invoice = client
-            .get_invoice(LndInvoiceRequestBody {
-                value: 1000.to_string(),
-                memo: Some("Hello".to_string()),
-                ..Default::default()
-            })
+            .get_invoice(LndInvoiceRequestBody::new(1000, Some("Hello".to_string())))
             .await?;
         info!("{:?}", invoice);
-        let subscription = client
-            .subscribe_to_invoice(invoice.r_hash_url_safe())
-            .await?;
+        let subscription = client.subscribe_to_invoice(&invoice.r_hash).await?;
         loop {
             match subscription.receiver.read::<LndInvoice>().await {
                 Some(LndWebsocketMessage::Response(state)) => {
@@ -275,13 +702,14 @@ mod test {
     #[tokio::test]
     #[traced_test]
     async fn get_hodl_invoice() -> anyhow::Result<()> {
-        let client = LightningClient::new("lnd.illuminodes.com", "./admin.macaroon").await?;
-        let ln_address = LightningAddress("42pupusas@blink.sv");
-        let pay_request = ln_address.get_invoice(&client.client, 1000).await?;
-        let _hodl_invoice = client.get_hodl_invoice(pay_request.r_hash()?, 100).await?;
-        let states = client
-            .subscribe_to_invoice(pay_request.r_hash_url_safe()?)
+        let lnd = MockLnd::start().await;
+        let client = lnd.client();
+        let ln_address = lnd.lightning_address("42pupusas");
+        let pay_request = LightningAddress(&ln_address)
+            .get_invoice(&client.client, 1000)
             .await?;
+        let _hodl_invoice = client.get_hodl_invoice(&pay_request.r_hash()?, 100).await?;
+        let states = client.subscribe_to_invoice(&pay_request.r_hash()?).await?;
         let mut correct_state = false;
         assert!(!correct_state);
         loop {
@@ -290,18 +718,16 @@ mod test {
             {
                 info!("{:?}", state.state());
                 match state.state() {
-                    HodlState::OPEN => {
-                        match client.cancel_htlc(pay_request.r_hash_url_safe()?).await {
-                            Ok(_) => {
-                                info!("Canceled");
-                                correct_state = true;
-                                break;
-                            }
-                            Err(e) => {
-                                error!("{}", e);
-                            }
+                    HodlState::OPEN => match client.cancel_htlc(&pay_request.r_hash()?).await {
+                        Ok(_) => {
+                            info!("Canceled");
+                            correct_state = true;
+                            break;
                         }
-                    }
+                        Err(e) => {
+                            error!("{}", e);
+                        }
+                    },
                     HodlState::CANCELED => {}
                     _ => {}
                 }
@@ -314,12 +740,13 @@ mod test {
     #[tokio::test]
     #[traced_test]
     async fn pay_invoice() -> anyhow::Result<()> {
-        let client = LightningClient::new("lnd.illuminodes.com", "./admin.macaroon").await?;
-        let ln_address = "42pupusas@blink.sv";
-        let pay_request = LightningAddress(ln_address)
+        let lnd = MockLnd::start().await;
+        let client = lnd.client();
+        let ln_address = lnd.lightning_address("42pupusas");
+        let pay_request = LightningAddress(&ln_address)
             .get_invoice(&client.client, 100000)
             .await?;
-        let pr = LndPaymentRequest::new(pay_request.pr.clone(), 10, 10.to_string(), false);
+        let pr = LndPaymentRequest::new(pay_request.pr.clone(), 10, 10, false);
         let lnd_ws = client.invoice_channel().await?;
         lnd_ws.sender.send(pr.clone()).await.unwrap();
         while let Some(LndWebsocketMessage::Response(state)) =
@@ -349,20 +776,22 @@ mod test {
     async fn settle_htlc() -> Result<(), anyhow::Error> {
         use std::sync::Arc;
         use tokio::sync::Mutex;
-        let client = LightningClient::new("lnd.illuminodes.com", "./admin.macaroon").await?;
-        let ln_address = "42pupusas@blink.sv";
-        let pay_request = LightningAddress(ln_address)
+        let lnd = MockLnd::start().await;
+        let client = lnd.client();
+        let ln_address = lnd.lightning_address("42pupusas");
+        let pay_request = LightningAddress(&ln_address)
             .get_invoice(&client.client, 10000)
             .await?;
 
-        let hodl_invoice = client.get_hodl_invoice(pay_request.r_hash()?, 20).await?;
+        let hodl_invoice = client.get_hodl_invoice(&pay_request.r_hash()?, 20).await?;
         info!("{:?}", hodl_invoice.payment_request());
         let correct_state = Arc::new(Mutex::new(false));
         let states = client
-            .subscribe_to_invoice(hodl_invoice.r_hash_url_safe()?)
+            .subscribe_to_invoice(&hodl_invoice.payment_hash())
             .await?;
+        lnd.simulate_payment(&hodl_invoice.payment_hash())?;
 
-        let pr = LndPaymentRequest::new(pay_request.pr.clone(), 1000, 10.to_string(), false);
+        let pr = LndPaymentRequest::new(pay_request.pr.clone(), 1000, 10, false);
         let lnd_ws = client.invoice_channel().await?;
         tokio::spawn(async move {
             loop {
@@ -377,7 +806,8 @@ mod test {
                                 info!("InFlight");
                             }
                             InvoicePaymentState::Succeeded => {
-                                client.settle_htlc(state.preimage()).await.unwrap();
+                                let preimage = state.preimage().unwrap();
+                                client.settle_htlc(&preimage).await.unwrap();
                                 break;
                             }
                             InvoicePaymentState::Failed => {
@@ -394,8 +824,10 @@ mod test {
         let correct_state_c = correct_state.clone();
         loop {
             info!("Waiting for state");
-            match states.receiver.read::<LndHodlInvoiceState>().await {
-                Some(LndWebsocketMessage::Response(state)) => match state.state() {
+            if let Some(LndWebsocketMessage::Response(state)) =
+                states.receiver.read::<LndHodlInvoiceState>().await
+            {
+                match state.state() {
                     HodlState::OPEN => {
                         info!("Open");
                     }
@@ -409,8 +841,7 @@ mod test {
                         break;
                     }
                     _ => {}
-                },
-                _ => {}
+                }
             }
         }
         assert!(*correct_state.lock().await);
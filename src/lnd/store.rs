@@ -0,0 +1,334 @@
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{lnd::models::optional, BrightResult, LndInvoiceState, PaymentHash, Preimage};
+
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or_default()
+}
+
+/// Invoice as recorded by an [`InvoiceStore`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct StoredInvoice {
+    pub payment_hash: PaymentHash,
+    /// Known for hodl invoices created with a local preimage and once an invoice
+    /// is settled through the client.
+    #[serde(with = "optional")]
+    pub preimage: Option<Preimage>,
+    pub payment_request: String,
+    pub amount_msat: u64,
+    pub state: LndInvoiceState,
+    pub hodl: bool,
+    /// Unix timestamp of creation.
+    pub created_at: u64,
+    /// Unix timestamp of the last state change.
+    pub updated_at: u64,
+}
+impl StoredInvoice {
+    pub fn new(payment_hash: PaymentHash, payment_request: String, amount_msat: u64) -> Self {
+        let now = unix_now();
+        Self {
+            payment_hash,
+            payment_request,
+            amount_msat,
+            created_at: now,
+            updated_at: now,
+            ..Default::default()
+        }
+    }
+    /// Whether the invoice can still be paid, settled or canceled.
+    pub fn is_pending(&self) -> bool {
+        matches!(
+            self.state,
+            LndInvoiceState::Open | LndInvoiceState::Accepted
+        )
+    }
+}
+
+/// Storage the client writes invoices and hodl preimages through to, so a
+/// restarted service can resume settling or canceling held payments.
+///
+/// Attached with [`crate::LightningClientBuilder::invoice_store`].
+pub trait InvoiceStore: Debug + Send + Sync {
+    /// Inserts or replaces the invoice with the same payment hash.
+    fn save(&self, invoice: StoredInvoice) -> BrightResult<()>;
+    fn get(&self, payment_hash: &PaymentHash) -> BrightResult<Option<StoredInvoice>>;
+    fn list(&self) -> BrightResult<Vec<StoredInvoice>>;
+    fn remove(&self, payment_hash: &PaymentHash) -> BrightResult<()>;
+    /// Records a new state, ignoring invoices that are not stored.
+    fn update_state(&self, payment_hash: &PaymentHash, state: LndInvoiceState) -> BrightResult<()> {
+        let Some(mut invoice) = self.get(payment_hash)? else {
+            return Ok(());
+        };
+        if invoice.state != state {
+            invoice.state = state;
+            invoice.updated_at = unix_now();
+            self.save(invoice)?;
+        }
+        Ok(())
+    }
+    /// Invoices that are still open or accepted.
+    fn pending(&self) -> BrightResult<Vec<StoredInvoice>> {
+        Ok(self
+            .list()?
+            .into_iter()
+            .filter(StoredInvoice::is_pending)
+            .collect())
+    }
+}
+
+/// [`InvoiceStore`] kept in memory, lost when the process exits.
+#[derive(Debug, Default)]
+pub struct MemoryInvoiceStore {
+    invoices: Mutex<HashMap<PaymentHash, StoredInvoice>>,
+}
+impl MemoryInvoiceStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl InvoiceStore for MemoryInvoiceStore {
+    fn save(&self, invoice: StoredInvoice) -> BrightResult<()> {
+        self.invoices
+            .lock()
+            .unwrap()
+            .insert(invoice.payment_hash, invoice);
+        Ok(())
+    }
+    fn get(&self, payment_hash: &PaymentHash) -> BrightResult<Option<StoredInvoice>> {
+        Ok(self.invoices.lock().unwrap().get(payment_hash).cloned())
+    }
+    fn list(&self) -> BrightResult<Vec<StoredInvoice>> {
+        let mut invoices = self
+            .invoices
+            .lock()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        invoices.sort_by_key(|invoice| (invoice.created_at, invoice.payment_hash));
+        Ok(invoices)
+    }
+    fn remove(&self, payment_hash: &PaymentHash) -> BrightResult<()> {
+        self.invoices.lock().unwrap().remove(payment_hash);
+        Ok(())
+    }
+}
+
+/// [`InvoiceStore`] persisted as a JSON file.
+///
+/// The whole file is rewritten on every change, through a temporary file that is
+/// synced and renamed into place so a crash never leaves it half written.
+#[derive(Debug)]
+pub struct FileInvoiceStore {
+    path: PathBuf,
+    memory: MemoryInvoiceStore,
+}
+impl FileInvoiceStore {
+    /// Opens the store at `path`, which is created on the first write.
+    pub fn open(path: impl AsRef<Path>) -> BrightResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        let memory = MemoryInvoiceStore::new();
+        if path.exists() {
+            let invoices: Vec<StoredInvoice> = serde_json::from_slice(&std::fs::read(&path)?)?;
+            for invoice in invoices {
+                memory.save(invoice)?;
+            }
+        }
+        Ok(Self { path, memory })
+    }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    fn flush(&self) -> BrightResult<()> {
+        let json = serde_json::to_vec_pretty(&self.memory.list()?)?;
+        let mut temporary = self.path.clone().into_os_string();
+        temporary.push(".tmp");
+        let mut file = std::fs::File::create(&temporary)?;
+        file.write_all(&json)?;
+        file.sync_all()?;
+        std::fs::rename(&temporary, &self.path)?;
+        Ok(())
+    }
+}
+impl InvoiceStore for FileInvoiceStore {
+    fn save(&self, invoice: StoredInvoice) -> BrightResult<()> {
+        self.memory.save(invoice)?;
+        self.flush()
+    }
+    fn get(&self, payment_hash: &PaymentHash) -> BrightResult<Option<StoredInvoice>> {
+        self.memory.get(payment_hash)
+    }
+    fn list(&self) -> BrightResult<Vec<StoredInvoice>> {
+        self.memory.list()
+    }
+    fn remove(&self, payment_hash: &PaymentHash) -> BrightResult<()> {
+        self.memory.remove(payment_hash)?;
+        self.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use futures_util::StreamExt;
+
+    use super::{FileInvoiceStore, InvoiceStore, MemoryInvoiceStore, StoredInvoice};
+    use crate::{
+        testing::MockLnd, BrightError, BrightResult, GrpcStatus, HodlEvent, LndInvoiceRequestBody,
+        LndInvoiceState, PaymentHash, Preimage,
+    };
+
+    #[tokio::test]
+    async fn client_writes_through_to_store() -> anyhow::Result<()> {
+        let lnd = MockLnd::start().await;
+        let store = Arc::new(MemoryInvoiceStore::new());
+        let client = lnd.client().with_invoice_store(store.clone());
+
+        let invoice = client
+            .get_invoice(LndInvoiceRequestBody::new(21, None))
+            .await?;
+        let stored = store.get(&invoice.r_hash)?.unwrap();
+        assert_eq!(stored.amount_msat, 21_000);
+        assert_eq!(stored.payment_request, invoice.payment_request);
+        assert!(!stored.hodl);
+        assert!(stored.preimage.is_none());
+
+        let hodl = client.hodl_invoice().value(50).create().await?;
+        let stored = store.get(&hodl.payment_hash())?.unwrap();
+        assert!(stored.hodl);
+        assert_eq!(stored.preimage, hodl.preimage());
+        assert_eq!(stored.state, LndInvoiceState::Open);
+        assert_eq!(store.pending()?.len(), 2);
+
+        lnd.simulate_payment(&hodl.payment_hash())?;
+        hodl.settle().await?;
+        let stored = store.get(&hodl.payment_hash())?.unwrap();
+        assert_eq!(stored.state, LndInvoiceState::Settled);
+        assert!(stored.updated_at >= stored.created_at);
+        assert_eq!(store.pending()?.len(), 1);
+        Ok(())
+    }
+    /// Fails every write of an invoice that has a payment request.
+    #[derive(Debug, Default)]
+    struct FailingStore(MemoryInvoiceStore);
+    impl InvoiceStore for FailingStore {
+        fn save(&self, invoice: StoredInvoice) -> BrightResult<()> {
+            if !invoice.payment_request.is_empty() {
+                return Err(BrightError::invalid_input("store unavailable"));
+            }
+            self.0.save(invoice)
+        }
+        fn get(&self, payment_hash: &PaymentHash) -> BrightResult<Option<StoredInvoice>> {
+            self.0.get(payment_hash)
+        }
+        fn list(&self) -> BrightResult<Vec<StoredInvoice>> {
+            self.0.list()
+        }
+        fn remove(&self, payment_hash: &PaymentHash) -> BrightResult<()> {
+            self.0.remove(payment_hash)
+        }
+    }
+
+    #[tokio::test]
+    async fn hodl_preimage_is_stored_before_invoice_is_created() -> anyhow::Result<()> {
+        let lnd = MockLnd::start().await;
+        let store = Arc::new(FailingStore::default());
+        let client = lnd.client().with_invoice_store(store.clone());
+
+        let hodl = client.hodl_invoice().value(50).create().await?;
+        let stored = store.get(&hodl.payment_hash())?.unwrap();
+        assert_eq!(stored.preimage, hodl.preimage());
+        assert!(stored.payment_request.is_empty());
+        lnd.simulate_payment(&hodl.payment_hash())?;
+        client.settle_stored(&hodl.payment_hash()).await?;
+        assert_eq!(
+            lnd.invoice_state(&hodl.payment_hash()),
+            Some(LndInvoiceState::Settled)
+        );
+
+        let store = Arc::new(MemoryInvoiceStore::new());
+        let client = lnd.client().with_invoice_store(store.clone());
+        let preimage = Preimage::from_bytes([3; 32]);
+        let hodl = client.hodl_invoice().preimage(preimage).create().await?;
+        let duplicate = client
+            .hodl_invoice()
+            .preimage(preimage)
+            .value(1)
+            .create()
+            .await;
+        assert!(matches!(duplicate, Err(BrightError::InvalidInput(_))));
+        let stored = store.get(&hodl.payment_hash())?.unwrap();
+        assert_eq!(stored.payment_request, hodl.payment_request());
+        assert_eq!(stored.preimage, Some(preimage));
+
+        // Refused by LND, which already has an invoice for the hash.
+        let fresh = Arc::new(MemoryInvoiceStore::new());
+        let duplicate = lnd
+            .client()
+            .with_invoice_store(fresh.clone())
+            .hodl_invoice()
+            .preimage(preimage)
+            .create()
+            .await;
+        assert_eq!(
+            duplicate.err().and_then(|e| e.status()),
+            Some(GrpcStatus::AlreadyExists)
+        );
+        assert!(fresh.get(&hodl.payment_hash())?.is_none());
+        Ok(())
+    }
+    #[tokio::test]
+    async fn file_store_resumes_settlement() -> anyhow::Result<()> {
+        let lnd = MockLnd::start().await;
+        let path = std::env::temp_dir().join(format!(
+            "bright_lightning_store_{}.json",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+
+        let client = lnd
+            .client()
+            .with_invoice_store(Arc::new(FileInvoiceStore::open(&path)?));
+        let hodl = client.hodl_invoice().value(75).create().await?;
+        lnd.simulate_payment(&hodl.payment_hash())?;
+        drop(client);
+
+        let store = Arc::new(FileInvoiceStore::open(&path)?);
+        assert_eq!(store.pending()?.len(), 1);
+        let client = lnd.client().with_invoice_store(store.clone());
+        let manager = client.hodl_manager();
+        assert_eq!(manager.resume()?, 1);
+        let mut events = Box::pin(manager.events());
+        assert!(matches!(
+            events.next().await.unwrap()?,
+            HodlEvent::Accepted(_)
+        ));
+        assert_eq!(
+            store.get(&hodl.payment_hash())?.unwrap().state,
+            LndInvoiceState::Accepted
+        );
+        client.settle_stored(&hodl.payment_hash()).await?;
+        assert_eq!(
+            lnd.invoice_state(&hodl.payment_hash()),
+            Some(LndInvoiceState::Settled)
+        );
+        let reopened = FileInvoiceStore::open(&path)?;
+        assert!(reopened.pending()?.is_empty());
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,292 @@
+use std::{collections::VecDeque, time::Duration};
+
+use futures_util::{stream, Stream, TryStreamExt};
+
+use super::{LightningClient, LndWebsocket, LndWebsocketMessage};
+use crate::{
+    BrightError, BrightResult, GrpcStatus, ListInvoicesQuery, LndInvoice, LndInvoiceState,
+};
+
+const MAX_RECONNECT_ATTEMPTS: u32 = 10;
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+struct InvoiceSubscription {
+    client: LightningClient,
+    add_index: u64,
+    settle_index: u64,
+    websocket: Option<LndWebsocket>,
+    connected: bool,
+    done: bool,
+    buffered: VecDeque<LndInvoice>,
+}
+impl InvoiceSubscription {
+    async fn connect(&mut self) -> BrightResult<LndWebsocket> {
+        let path = format!(
+            "/v1/invoices/subscribe?add_index={}&settle_index={}",
+            self.add_index, self.settle_index
+        );
+        let mut attempt = 0;
+        loop {
+            match self.client.websocket(&path).await {
+                Ok(websocket) => break Ok(websocket),
+                Err(e) if !self.connected || attempt >= MAX_RECONNECT_ATTEMPTS => break Err(e),
+                Err(e) => {
+                    let delay = Duration::from_millis(250 << attempt.min(7));
+                    tracing::warn!("Invoice subscription reconnect failed: {}", e);
+                    tokio::time::sleep(delay.min(MAX_RECONNECT_DELAY)).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+    /// Every settled invoice of the node, paged through with `index_offset` and
+    /// ordered by settle index.
+    async fn settled_invoices(&self) -> BrightResult<Vec<LndInvoice>> {
+        let mut settled = self
+            .client
+            .list_invoices_stream(ListInvoicesQuery::new())
+            .try_filter(|invoice| std::future::ready(invoice.state == LndInvoiceState::Settled))
+            .try_collect::<Vec<_>>()
+            .await?;
+        settled.sort_by_key(|invoice| invoice.settle_index);
+        Ok(settled)
+    }
+    async fn reconnect(&mut self) -> BrightResult<()> {
+        // LND only replays settlements after a non zero settle index, so the stream
+        // starts from the node's highest one.
+        if !self.connected && self.settle_index == 0 {
+            let settled = self.settled_invoices().await?;
+            self.settle_index = settled.last().map(|i| i.settle_index).unwrap_or_default();
+        }
+        let websocket = self.connect().await?;
+        // The node had not settled any invoice when the stream started, so every
+        // settlement is new.
+        if self.connected && self.settle_index == 0 {
+            self.buffered.extend(self.settled_invoices().await?);
+        }
+        self.websocket = Some(websocket);
+        self.connected = true;
+        Ok(())
+    }
+    /// Tracks the indices of `invoice`, returning false if it was already reported.
+    fn record(&mut self, invoice: &LndInvoice) -> bool {
+        match invoice.state {
+            LndInvoiceState::Settled => {
+                if invoice.settle_index <= self.settle_index {
+                    return false;
+                }
+                self.settle_index = invoice.settle_index;
+                true
+            }
+            LndInvoiceState::Canceled => false,
+            LndInvoiceState::Open | LndInvoiceState::Accepted => {
+                if invoice.add_index <= self.add_index {
+                    return false;
+                }
+                self.add_index = invoice.add_index;
+                true
+            }
+        }
+    }
+    async fn next(&mut self) -> Option<BrightResult<LndInvoice>> {
+        loop {
+            if let Some(invoice) = self.buffered.pop_front() {
+                if self.record(&invoice) {
+                    return Some(Ok(invoice));
+                }
+                continue;
+            }
+            if self.done {
+                return None;
+            }
+            let Some(websocket) = self.websocket.take() else {
+                if let Err(e) = self.reconnect().await {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+                continue;
+            };
+            match websocket.receiver.read::<LndInvoice>().await {
+                Some(LndWebsocketMessage::Response(invoice)) => {
+                    self.websocket = Some(websocket);
+                    self.buffered.push_back(invoice);
+                }
+                Some(LndWebsocketMessage::Ping) => self.websocket = Some(websocket),
+                Some(LndWebsocketMessage::Error(e)) if e.status() != GrpcStatus::Unavailable => {
+                    self.done = true;
+                    return Some(Err(BrightError::from(e)));
+                }
+                Some(LndWebsocketMessage::Error(e)) => {
+                    tracing::warn!("Invoice subscription dropped: {}", e.message());
+                }
+                None => tracing::debug!("Invoice subscription closed, reconnecting"),
+            }
+        }
+    }
+}
+
+impl LightningClient {
+    /// Streams invoices from `/v1/invoices/subscribe` as they are added and settled.
+    ///
+    /// Invoices added after `add_index` and settled after `settle_index` are replayed
+    /// first, zero meaning only new events. A zero `settle_index` starts from the
+    /// node's highest settle index. The stream keeps track of the indices it has
+    /// seen and resumes from them when the websocket drops, so settlements are not
+    /// lost across reconnects. It ends after an error.
+    pub fn subscribe_invoices(
+        &self,
+        add_index: u64,
+        settle_index: u64,
+    ) -> impl Stream<Item = BrightResult<LndInvoice>> + Send + 'static {
+        let subscription = InvoiceSubscription {
+            client: self.clone(),
+            add_index,
+            settle_index,
+            websocket: None,
+            connected: false,
+            done: false,
+            buffered: VecDeque::new(),
+        };
+        stream::unfold(subscription, |mut subscription| async move {
+            let next = subscription.next().await?;
+            Some((next, subscription))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures_util::StreamExt;
+
+    use crate::{testing::MockLnd, LndInvoiceRequestBody, LndInvoiceState};
+
+    #[tokio::test]
+    async fn invoice_stream_resumes_after_reconnect() -> anyhow::Result<()> {
+        let lnd = MockLnd::start().await;
+        let client = lnd.client();
+        let first = client
+            .get_invoice(LndInvoiceRequestBody::new(1000, None))
+            .await?;
+        let second = client
+            .get_invoice(LndInvoiceRequestBody::new(2000, None))
+            .await?;
+        let mut invoices = Box::pin(client.subscribe_invoices(first.add_index.parse()?, 0));
+        let added = invoices.next().await.unwrap()?;
+        assert_eq!(added.r_hash, second.r_hash);
+        assert_eq!(added.state, LndInvoiceState::Open);
+
+        // Settled while disconnected, before any settle index is known.
+        lnd.drop_subscriptions();
+        lnd.simulate_payment(&second.r_hash)?;
+        let third = client
+            .get_invoice(LndInvoiceRequestBody::new(3000, None))
+            .await?;
+        let settled = invoices.next().await.unwrap()?;
+        assert_eq!(settled.r_hash, second.r_hash);
+        assert_eq!(settled.state, LndInvoiceState::Settled);
+        assert_eq!(invoices.next().await.unwrap()?.r_hash, third.r_hash);
+
+        // Resumed from the last add and settle indices.
+        lnd.drop_subscriptions();
+        let fourth = client
+            .get_invoice(LndInvoiceRequestBody::new(4000, None))
+            .await?;
+        lnd.simulate_payment(&third.r_hash)?;
+        let added = invoices.next().await.unwrap()?;
+        assert_eq!(added.r_hash, fourth.r_hash);
+        let settled = invoices.next().await.unwrap()?;
+        assert_eq!(settled.r_hash, third.r_hash);
+        assert_eq!(settled.state, LndInvoiceState::Settled);
+
+        // Live events after the reconnect.
+        lnd.simulate_payment(&fourth.r_hash)?;
+        let settled = invoices.next().await.unwrap()?;
+        assert_eq!(settled.r_hash, fourth.r_hash);
+        assert_eq!(settled.settle_index, 3);
+        Ok(())
+    }
+    #[tokio::test]
+    async fn invoice_stream_replays_settlements_of_older_invoices() -> anyhow::Result<()> {
+        let lnd = MockLnd::start().await;
+        let client = lnd.client();
+        let settled = client
+            .get_invoice(LndInvoiceRequestBody::new(1000, None))
+            .await?;
+        lnd.simulate_payment(&settled.r_hash)?;
+        let older = client
+            .get_invoice(LndInvoiceRequestBody::new(2000, None))
+            .await?;
+        let marker = client
+            .get_invoice(LndInvoiceRequestBody::new(3000, None))
+            .await?;
+        let mut invoices = Box::pin(client.subscribe_invoices(older.add_index.parse()?, 0));
+        assert_eq!(invoices.next().await.unwrap()?.r_hash, marker.r_hash);
+
+        // Created before the stream and settled while it was disconnected.
+        lnd.drop_subscriptions();
+        lnd.simulate_payment(&older.r_hash)?;
+        let replayed = invoices.next().await.unwrap()?;
+        assert_eq!(replayed.r_hash, older.r_hash);
+        assert_eq!(replayed.state, LndInvoiceState::Settled);
+        assert_eq!(replayed.settle_index, 2);
+        Ok(())
+    }
+    #[tokio::test]
+    async fn invoice_stream_seeds_from_highest_settle_index() -> anyhow::Result<()> {
+        let lnd = MockLnd::start().await;
+        let client = lnd.client();
+        let oldest = client
+            .get_invoice(LndInvoiceRequestBody::new(1000, None))
+            .await?;
+        // Pushes the oldest invoice out of the newest page of invoices.
+        let mut newer = vec![];
+        for _ in 0..100 {
+            newer.push(
+                client
+                    .get_invoice(LndInvoiceRequestBody::new(2000, None))
+                    .await?,
+            );
+        }
+        lnd.simulate_payment(&newer[0].r_hash)?;
+        lnd.simulate_payment(&oldest.r_hash)?;
+        let mut invoices = Box::pin(client.subscribe_invoices(newer[98].add_index.parse()?, 0));
+        assert_eq!(invoices.next().await.unwrap()?.r_hash, newer[99].r_hash);
+
+        // Neither earlier settlement is replayed as new.
+        lnd.simulate_payment(&newer[1].r_hash)?;
+        let settled = invoices.next().await.unwrap()?;
+        assert_eq!(settled.r_hash, newer[1].r_hash);
+        assert_eq!(settled.settle_index, 3);
+        Ok(())
+    }
+    #[tokio::test]
+    async fn invoice_stream_keeps_adds_replayed_after_settlements() -> anyhow::Result<()> {
+        let lnd = MockLnd::start().await;
+        let client = lnd.client();
+        let first = client
+            .get_invoice(LndInvoiceRequestBody::new(1000, None))
+            .await?;
+        let marker = client
+            .get_invoice(LndInvoiceRequestBody::new(1000, None))
+            .await?;
+        let mut invoices = Box::pin(client.subscribe_invoices(first.add_index.parse()?, 0));
+        assert_eq!(invoices.next().await.unwrap()?.r_hash, marker.r_hash);
+
+        // Both added while disconnected, the later one also settled.
+        lnd.drop_subscriptions();
+        let added = client
+            .get_invoice(LndInvoiceRequestBody::new(2000, None))
+            .await?;
+        let settled = client
+            .get_invoice(LndInvoiceRequestBody::new(3000, None))
+            .await?;
+        lnd.simulate_payment(&settled.r_hash)?;
+        let next = invoices.next().await.unwrap()?;
+        assert_eq!(next.r_hash, settled.r_hash);
+        assert_eq!(next.state, LndInvoiceState::Settled);
+        let next = invoices.next().await.unwrap()?;
+        assert_eq!(next.r_hash, added.r_hash);
+        assert_eq!(next.state, LndInvoiceState::Open);
+        Ok(())
+    }
+}
@@ -0,0 +1,129 @@
+use std::path::Path;
+
+use base64::prelude::*;
+
+use crate::BrightResult;
+
+/// TLS settings shared by the REST and websocket transports.
+///
+/// By default the node certificate is verified against the system roots, which works
+/// for nodes behind a proxy with a public certificate. LND's own self-signed
+/// `tls.cert` must be pinned with [`LndTlsConfig::pinned`] or
+/// [`LndTlsConfig::from_cert_path`].
+#[derive(Debug, Clone, Default)]
+pub struct LndTlsConfig {
+    cert_pem: Option<Vec<u8>>,
+    danger_accept_invalid_certs: bool,
+}
+impl LndTlsConfig {
+    /// Trust only the given PEM encoded certificate.
+    pub fn pinned(cert_pem: impl Into<Vec<u8>>) -> Self {
+        Self {
+            cert_pem: Some(cert_pem.into()),
+            danger_accept_invalid_certs: false,
+        }
+    }
+    /// Trust only the given DER encoded certificate, as carried by lndconnect URIs.
+    pub fn pinned_der(cert_der: &[u8]) -> Self {
+        let encoded = BASE64_STANDARD.encode(cert_der);
+        let mut pem = String::from("-----BEGIN CERTIFICATE-----\n");
+        for line in encoded.as_bytes().chunks(64) {
+            pem.push_str(std::str::from_utf8(line).unwrap_or_default());
+            pem.push('\n');
+        }
+        pem.push_str("-----END CERTIFICATE-----\n");
+        Self::pinned(pem)
+    }
+    pub fn from_cert_path(path: impl AsRef<Path>) -> BrightResult<Self> {
+        Ok(Self::pinned(std::fs::read(path)?))
+    }
+    /// Disables certificate validation entirely, leaving the macaroon open to
+    /// interception. Only meant for local development.
+    pub fn danger_accept_invalid_certs() -> Self {
+        Self {
+            cert_pem: None,
+            danger_accept_invalid_certs: true,
+        }
+    }
+    pub fn cert_pem(&self) -> Option<&[u8]> {
+        self.cert_pem.as_deref()
+    }
+    pub fn accepts_invalid_certs(&self) -> bool {
+        self.danger_accept_invalid_certs
+    }
+    pub(crate) fn configure_reqwest(
+        &self,
+        builder: reqwest::ClientBuilder,
+    ) -> BrightResult<reqwest::ClientBuilder> {
+        if self.danger_accept_invalid_certs {
+            tracing::warn!("TLS certificate validation is disabled for the LND connection");
+            return Ok(builder.danger_accept_invalid_certs(true));
+        }
+        match &self.cert_pem {
+            Some(pem) => Ok(builder
+                .tls_built_in_root_certs(false)
+                .add_root_certificate(reqwest::Certificate::from_pem(pem)?)),
+            None => Ok(builder),
+        }
+    }
+    pub(crate) fn connector(&self) -> BrightResult<native_tls::TlsConnector> {
+        let mut builder = native_tls::TlsConnector::builder();
+        if self.danger_accept_invalid_certs {
+            tracing::warn!("TLS certificate validation is disabled for the LND connection");
+            builder.danger_accept_invalid_certs(true);
+        } else if let Some(pem) = &self.cert_pem {
+            builder
+                .disable_built_in_roots(true)
+                .add_root_certificate(native_tls::Certificate::from_pem(pem)?);
+        }
+        Ok(builder.build()?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::LndTlsConfig;
+    use crate::testing::{MockResponse, MockServer};
+    use tokio::net::TcpStream;
+
+    async fn spawn_server() -> MockServer {
+        MockServer::start(|_| MockResponse::ok(r#"{"identity_pubkey":"02aa","block_height":100}"#))
+            .await
+    }
+    async fn handshake(tls: &LndTlsConfig, port: u16) -> anyhow::Result<()> {
+        let connector = tokio_native_tls::TlsConnector::from(tls.connector()?);
+        let stream = TcpStream::connect(("127.0.0.1", port)).await?;
+        connector.connect("localhost", stream).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn pinned_cert_is_accepted() -> anyhow::Result<()> {
+        let server = spawn_server().await;
+        handshake(&server.tls(), server.port).await?;
+        server.client().get_info().await?;
+        Ok(())
+    }
+    #[tokio::test]
+    async fn mismatched_cert_is_rejected() -> anyhow::Result<()> {
+        let server = spawn_server().await;
+        let other = spawn_server().await;
+        assert!(handshake(&other.tls(), server.port).await.is_err());
+        let client = crate::LightningClient::builder()
+            .host("localhost")
+            .port(server.port)
+            .tls(other.tls())
+            .build()?;
+        assert!(client.get_info().await.is_err());
+        Ok(())
+    }
+    #[tokio::test]
+    async fn self_signed_needs_explicit_opt_in() -> anyhow::Result<()> {
+        let server = spawn_server().await;
+        assert!(handshake(&LndTlsConfig::default(), server.port)
+            .await
+            .is_err());
+        handshake(&LndTlsConfig::danger_accept_invalid_certs(), server.port).await?;
+        Ok(())
+    }
+}
@@ -9,7 +9,7 @@ use serde::{de::DeserializeOwned, Serialize};
 use tokio::{net::TcpStream, sync::RwLock};
 use tokio_tungstenite::{tungstenite::Message, MaybeTlsStream, WebSocketStream};
 
-use crate::{LndError, LndResponse};
+use crate::{BrightError, BrightResult, GrpcStatus, LndError, LndResponse, LndTlsConfig};
 
 type LndWebsocketWriterHalf =
     Option<SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>>;
@@ -21,20 +21,20 @@ impl LndWebsocketWriter {
     pub fn new(writer: LndWebsocketWriterHalf) -> Self {
         Self(Arc::new(RwLock::new(writer)))
     }
-    pub async fn send<S>(&self, message: S) -> anyhow::Result<()>
+    pub async fn send<S>(&self, message: S) -> BrightResult<()>
     where
         S: TryInto<String> + Send + Sync + 'static,
         <S as TryInto<std::string::String>>::Error:
             std::marker::Send + std::fmt::Debug + std::marker::Sync,
     {
-        let message_string = message
-            .try_into()
-            .map_err(|_e| anyhow::anyhow!("Could not parse"))?;
+        let message_string = message.try_into().map_err(|e| {
+            BrightError::invalid_input(format!("Could not serialize message: {:?}", e))
+        })?;
         let message = Message::Text(message_string.into());
         if let Some(writer) = self.0.write().await.as_mut() {
             writer.send(message).await.map_err(|e| e.into())
         } else {
-            Err(anyhow::anyhow!("No writer"))
+            Err(BrightError::invalid_input("Websocket is not connected"))
         }
     }
 }
@@ -70,9 +70,10 @@ impl LndWebsocketReader {
                 Message::Ping(_) => Some(LndWebsocketMessage::Ping),
                 _ => None,
             },
-            Err(e) => Some(LndWebsocketMessage::Error(
-                LndError::try_from(e.to_string()).unwrap(),
-            )),
+            Err(e) => Some(LndWebsocketMessage::Error(LndError::new(
+                GrpcStatus::Unavailable,
+                e.to_string(),
+            ))),
         }
     }
 }
@@ -88,6 +89,11 @@ pub struct LndWebsocket {
     pub sender: LndWebsocketWriter,
 }
 
+impl Default for LndWebsocket {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 impl LndWebsocket {
     pub fn new() -> Self {
         Self {
@@ -100,7 +106,8 @@ impl LndWebsocket {
         url: String,
         macaroon: String,
         request: String,
-    ) -> anyhow::Result<Self> {
+        tls: &LndTlsConfig,
+    ) -> BrightResult<Self> {
         let random_key = "dGhlIHNhbXBsZSBub25jZQ2342qdfsdgfsdfg";
         let mut headers = [
             Header {
@@ -139,9 +146,7 @@ impl LndWebsocket {
                 .accept_unmasked_frames(true),
         );
 
-        let tls = native_tls::TlsConnector::builder()
-            .danger_accept_invalid_certs(true)
-            .build()?;
+        let tls = tls.connector()?;
         let (ws, _response) = tokio_tungstenite::connect_async_tls_with_config(
             req,
             danger_conf,
@@ -160,19 +165,19 @@ mod test {
 
     use super::LndWebsocketMessage;
     use crate::LndHodlInvoiceState;
-    use std::io::Read;
     use tracing_test::traced_test;
 
     #[tokio::test]
     #[traced_test]
     async fn check_invoice_paid() -> Result<(), anyhow::Error> {
-        let url = "lnd.illuminodes.com";
-        let client = crate::lnd::rest_client::LightningClient::new(url, "./admin.macaroon").await?;
+        let lnd = crate::testing::MockLnd::start().await;
+        let client = lnd.client();
+        let url = client.url();
         let invoice = client
-            .get_invoice(crate::LndInvoiceRequestBody {
-                value: 1000.to_string(),
-                memo: Some("Hello".to_string()),
-            })
+            .get_invoice(crate::LndInvoiceRequestBody::new(
+                1000,
+                Some("Hello".to_string()),
+            ))
             .await?;
         tracing::info!("Invoice: {}", invoice);
         let query = format!(
@@ -180,14 +185,12 @@ mod test {
             url,
             invoice.r_hash_url_safe()
         );
-        let mut macaroon = vec![];
-        let mut file = std::fs::File::open("./admin.macaroon")?;
-        file.read_to_end(&mut macaroon)?;
         let lnd_ws = super::LndWebsocket::new()
             .connect(
                 url.to_string(),
-                macaroon.iter().map(|b| format!("{:02x}", b)).collect(),
+                client.macaroon().to_string(),
                 query,
+                &lnd.tls(),
             )
             .await?;
         loop {
@@ -197,15 +200,13 @@ mod test {
                     break;
                 }
                 Some(LndWebsocketMessage::Error(e)) => {
-                    tracing::error!("Error: {}", e);
-                    assert!(false);
+                    panic!("Error: {}", e);
                 }
                 Some(LndWebsocketMessage::Ping) => {
                     tracing::info!("Ping");
                 }
                 None => {
-                    tracing::info!("None");
-                    assert!(false);
+                    panic!("None");
                 }
             }
         }
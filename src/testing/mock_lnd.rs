@@ -0,0 +1,1407 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use base64::prelude::*;
+use bitcoin::{
+    hashes::{sha256, Hash},
+    secp256k1::{Secp256k1, SecretKey},
+};
+use futures_util::{future::BoxFuture, SinkExt, StreamExt};
+use lightning_invoice::{
+    Bolt11Invoice, Bolt11InvoiceDescription, Currency, InvoiceBuilder, PaymentSecret,
+};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tokio::sync::{mpsc, Notify};
+use tokio_tungstenite::tungstenite::Message;
+
+use super::{MockHandler, MockRequest, MockResponse, MockServer, MockWebsocket, MOCK_MACAROON};
+use crate::{
+    BrightError, BrightResult, LightningClient, LndInvoiceState, LndTlsConfig, PaymentHash,
+};
+
+const NODE_KEY: [u8; 32] = [0x11; 32];
+const REMOTE_KEY: [u8; 32] = [0x22; 32];
+const ROUTING_KEY: [u8; 32] = [0x44; 32];
+const MOCK_CHAN_ID: u64 = 124244814004224;
+const KEYSEND_RECORD: &str = "5482373484";
+const DEFAULT_CLTV_EXPIRY: u64 = 80;
+const DEFAULT_EXPIRY: u64 = 86400;
+
+/// Status update sent by the mock router for a scripted payment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MockPaymentUpdate {
+    Initiated,
+    InFlight,
+    Succeeded,
+    /// Fails the payment with an LND failure reason, e.g. `FAILURE_REASON_NO_ROUTE`.
+    Failed(String),
+}
+impl MockPaymentUpdate {
+    fn status(&self) -> &'static str {
+        match self {
+            MockPaymentUpdate::Initiated => "INITIATED",
+            MockPaymentUpdate::InFlight => "IN_FLIGHT",
+            MockPaymentUpdate::Succeeded => "SUCCEEDED",
+            MockPaymentUpdate::Failed(_) => "FAILED",
+        }
+    }
+}
+
+struct MockInvoice {
+    r_hash: [u8; 32],
+    r_preimage: Option<[u8; 32]>,
+    payment_addr: [u8; 32],
+    payment_request: String,
+    memo: String,
+    value_msat: u64,
+    expiry: u64,
+    cltv_expiry: u64,
+    private: bool,
+    description_hash: Vec<u8>,
+    fallback_addr: String,
+    is_amp: bool,
+    is_hodl: bool,
+    add_index: u64,
+    settle_index: u64,
+    creation_date: u64,
+    settle_date: u64,
+    amt_paid_msat: u64,
+    state: LndInvoiceState,
+    htlcs: Vec<Value>,
+}
+impl MockInvoice {
+    fn to_json(&self) -> Value {
+        json!({
+            "memo": self.memo,
+            "r_preimage": self.r_preimage.map(|p| BASE64_STANDARD.encode(p)).unwrap_or_default(),
+            "r_hash": BASE64_STANDARD.encode(self.r_hash),
+            "value": (self.value_msat / 1000).to_string(),
+            "value_msat": self.value_msat.to_string(),
+            "settled": self.state == LndInvoiceState::Settled,
+            "creation_date": self.creation_date.to_string(),
+            "settle_date": self.settle_date.to_string(),
+            "payment_request": self.payment_request,
+            "description_hash": BASE64_STANDARD.encode(&self.description_hash),
+            "expiry": self.expiry.to_string(),
+            "fallback_addr": self.fallback_addr,
+            "cltv_expiry": self.cltv_expiry.to_string(),
+            "route_hints": [],
+            "private": self.private,
+            "add_index": self.add_index.to_string(),
+            "settle_index": self.settle_index.to_string(),
+            "amt_paid": self.amt_paid_msat.to_string(),
+            "amt_paid_sat": (self.amt_paid_msat / 1000).to_string(),
+            "amt_paid_msat": self.amt_paid_msat.to_string(),
+            "state": self.state,
+            "htlcs": self.htlcs,
+            "features": {
+                "9": { "name": "tlv-onion", "is_required": false, "is_known": true },
+                "14": { "name": "payment-addr", "is_required": true, "is_known": true },
+            },
+            "is_keysend": false,
+            "payment_addr": BASE64_STANDARD.encode(self.payment_addr),
+            "is_amp": self.is_amp,
+            "amp_invoice_state": {},
+        })
+    }
+}
+
+/// Payment sent through the mock router, resolved from an invoice or keysend.
+struct MockSend {
+    hash: [u8; 32],
+    preimage: Option<[u8; 32]>,
+    value_msat: u64,
+    is_remote: bool,
+    payee: String,
+    final_cltv_delta: u64,
+    payment_addr: Option<[u8; 32]>,
+    custom_records: Value,
+}
+
+/// Payment recorded by the mock router, for tracking and listing.
+struct MockPayment {
+    hash: [u8; 32],
+    preimage: Option<[u8; 32]>,
+    hops: Vec<Value>,
+    route: Value,
+    /// Fields that do not change with the status.
+    base: Value,
+    latest: Value,
+}
+impl MockPayment {
+    fn to_json(&self, update: &MockPaymentUpdate) -> Value {
+        let succeeded = update == &MockPaymentUpdate::Succeeded;
+        let attempt_time_ns = &self.base["creation_time_ns"];
+        let htlc_status = match update {
+            MockPaymentUpdate::Initiated => None,
+            MockPaymentUpdate::InFlight => Some("IN_FLIGHT"),
+            MockPaymentUpdate::Succeeded => Some("SUCCEEDED"),
+            MockPaymentUpdate::Failed(_) => Some("FAILED"),
+        };
+        let htlcs = htlc_status
+            .map(|status| {
+                vec![json!({
+                    "attempt_id": self.base["payment_index"],
+                    "status": status,
+                    "route": self.route,
+                    "attempt_time_ns": attempt_time_ns,
+                    "resolve_time_ns": if status == "IN_FLIGHT" { json!("0") } else { attempt_time_ns.clone() },
+                    "failure": match update {
+                        MockPaymentUpdate::Failed(reason) => htlc_failure(reason, &self.hops),
+                        _ => Value::Null,
+                    },
+                    "preimage": match (succeeded, self.preimage) {
+                        (true, Some(preimage)) => BASE64_STANDARD.encode(preimage),
+                        _ => String::new(),
+                    },
+                })]
+            })
+            .unwrap_or_default();
+        let mut payment = self.base.clone();
+        payment["status"] = json!(update.status());
+        payment["payment_preimage"] = match (succeeded, self.preimage) {
+            (true, Some(preimage)) => json!(hex(&preimage)),
+            _ => json!("0".repeat(64)),
+        };
+        payment["failure_reason"] = match update {
+            MockPaymentUpdate::Failed(reason) => json!(reason),
+            _ => json!("FAILURE_REASON_NONE"),
+        };
+        payment["htlcs"] = json!(htlcs);
+        payment
+    }
+    fn is_final(&self) -> bool {
+        matches!(self.latest["status"].as_str(), Some("SUCCEEDED" | "FAILED"))
+    }
+}
+
+struct RemoteInvoice {
+    preimage: [u8; 32],
+}
+
+#[derive(Default)]
+struct MockLndState {
+    port: u16,
+    block_height: u32,
+    counter: u64,
+    invoices: Vec<MockInvoice>,
+    remote_invoices: HashMap<[u8; 32], RemoteInvoice>,
+    payment_scripts: HashMap<String, Vec<MockPaymentUpdate>>,
+    payments: Vec<MockPayment>,
+    /// Trackers of one payment, or of all payments when no hash is set.
+    payment_subscribers: Vec<(Option<[u8; 32]>, mpsc::UnboundedSender<Value>)>,
+    /// Woken whenever a tracker of all payments connects.
+    payment_tracker_connected: Arc<Notify>,
+    addresses: Vec<(String, String, String)>,
+    invoice_subscribers: Vec<([u8; 32], mpsc::UnboundedSender<Value>)>,
+    all_invoices_subscribers: Vec<mpsc::UnboundedSender<Value>>,
+}
+impl MockLndState {
+    fn next_bytes(&mut self, tag: &str) -> [u8; 32] {
+        self.counter += 1;
+        Sha256::digest(format!("bright-lightning-mock-{}-{}", tag, self.counter)).into()
+    }
+    fn next_add_index(&self) -> u64 {
+        self.invoices
+            .iter()
+            .map(|i| i.add_index)
+            .max()
+            .unwrap_or_default()
+            + 1
+    }
+    fn next_settle_index(&self) -> u64 {
+        self.invoices
+            .iter()
+            .map(|i| i.settle_index)
+            .max()
+            .unwrap_or_default()
+            + 1
+    }
+    fn invoice_mut(&mut self, hash: &[u8; 32]) -> Option<&mut MockInvoice> {
+        self.invoices.iter_mut().find(|i| &i.r_hash == hash)
+    }
+    fn notify(&mut self, hash: &[u8; 32]) {
+        let Some(invoice) = self.invoices.iter().find(|i| &i.r_hash == hash) else {
+            return;
+        };
+        let update = invoice.to_json();
+        self.invoice_subscribers.retain(|(subscribed, sender)| {
+            subscribed != hash || sender.send(update.clone()).is_ok()
+        });
+        // The global subscription only reports new and settled invoices.
+        if matches!(
+            invoice.state,
+            LndInvoiceState::Open | LndInvoiceState::Settled
+        ) {
+            self.all_invoices_subscribers
+                .retain(|sender| sender.send(update.clone()).is_ok());
+        }
+    }
+    fn add_invoice(&mut self, body: &Value, hodl_hash: Option<[u8; 32]>) -> MockResponse {
+        let (r_hash, r_preimage) = match (hodl_hash, json_bytes(body, "r_preimage")) {
+            (Some(hash), _) => (hash, None),
+            (None, Some(preimage)) => match <[u8; 32]>::try_from(preimage) {
+                Ok(preimage) => (Sha256::digest(preimage).into(), Some(preimage)),
+                Err(_) => return MockResponse::lnd_error(500, 2, "invalid preimage length"),
+            },
+            (None, None) => {
+                let preimage = self.next_bytes("preimage");
+                (Sha256::digest(preimage).into(), Some(preimage))
+            }
+        };
+        if self.invoices.iter().any(|i| i.r_hash == r_hash) {
+            return MockResponse::lnd_error(409, 6, "invoice with payment hash already exists");
+        }
+        let value_msat = json_u64(body, "value_msat")
+            .filter(|v| *v > 0)
+            .or_else(|| json_u64(body, "value").map(|v| v * 1000))
+            .unwrap_or_default();
+        let memo = body["memo"].as_str().unwrap_or_default().to_string();
+        let expiry = json_u64(body, "expiry")
+            .filter(|v| *v > 0)
+            .unwrap_or(DEFAULT_EXPIRY);
+        let cltv_expiry = json_u64(body, "cltv_expiry")
+            .filter(|v| *v > 0)
+            .unwrap_or(DEFAULT_CLTV_EXPIRY);
+        let description_hash = json_bytes(body, "description_hash").unwrap_or_default();
+        let payment_addr = self.next_bytes("payment_addr");
+        let payment_request = bolt11(
+            &NODE_KEY,
+            r_hash,
+            payment_addr,
+            value_msat,
+            &memo,
+            expiry,
+            cltv_expiry,
+        );
+        let invoice = MockInvoice {
+            r_hash,
+            r_preimage,
+            payment_addr,
+            payment_request,
+            memo,
+            value_msat,
+            expiry,
+            cltv_expiry,
+            private: body["private"].as_bool().unwrap_or_default(),
+            description_hash,
+            fallback_addr: body["fallback_addr"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            is_amp: body["is_amp"].as_bool().unwrap_or_default(),
+            is_hodl: hodl_hash.is_some(),
+            add_index: self.next_add_index(),
+            settle_index: 0,
+            creation_date: now(),
+            settle_date: 0,
+            amt_paid_msat: 0,
+            state: LndInvoiceState::Open,
+            htlcs: vec![],
+        };
+        let response = json!({
+            "r_hash": BASE64_STANDARD.encode(invoice.r_hash),
+            "payment_request": invoice.payment_request,
+            "add_index": invoice.add_index.to_string(),
+            "payment_addr": BASE64_STANDARD.encode(invoice.payment_addr),
+        });
+        self.invoices.push(invoice);
+        self.notify(&r_hash);
+        MockResponse::json(&response)
+    }
+    fn simulate_payment(&mut self, hash: &[u8; 32]) -> BrightResult<()> {
+        let block_height = self.block_height as u64;
+        let settle_index = self.next_settle_index();
+        let invoice = self
+            .invoice_mut(hash)
+            .ok_or_else(|| BrightError::invalid_input("Unknown invoice"))?;
+        if invoice.state != LndInvoiceState::Open {
+            return Err(BrightError::invalid_input("Invoice is not open"));
+        }
+        invoice.amt_paid_msat = invoice.value_msat;
+        invoice.htlcs = vec![json!({
+            "chan_id": MOCK_CHAN_ID.to_string(),
+            "htlc_index": invoice.add_index.to_string(),
+            "amt_msat": invoice.value_msat.to_string(),
+            "accept_height": block_height,
+            "accept_time": now().to_string(),
+            "resolve_time": "0",
+            "expiry_height": block_height + invoice.cltv_expiry,
+            "state": "ACCEPTED",
+            "custom_records": {},
+            "mpp_total_amt_msat": invoice.value_msat.to_string(),
+            "amp": null,
+        })];
+        if invoice.is_hodl {
+            invoice.state = LndInvoiceState::Accepted;
+        } else {
+            settle(invoice, settle_index);
+        }
+        self.notify(hash);
+        Ok(())
+    }
+    fn settle_invoice(&mut self, body: &Value) -> MockResponse {
+        let Some(preimage) =
+            json_bytes(body, "preimage").and_then(|p| <[u8; 32]>::try_from(p).ok())
+        else {
+            return MockResponse::lnd_error(500, 2, "invalid preimage");
+        };
+        let hash: [u8; 32] = Sha256::digest(preimage).into();
+        let settle_index = self.next_settle_index();
+        let Some(invoice) = self.invoice_mut(&hash) else {
+            return MockResponse::lnd_error(404, 5, "unable to locate invoice");
+        };
+        match invoice.state {
+            LndInvoiceState::Accepted => {
+                invoice.r_preimage = Some(preimage);
+                settle(invoice, settle_index);
+            }
+            LndInvoiceState::Settled => {
+                return MockResponse::lnd_error(500, 2, "invoice already settled")
+            }
+            LndInvoiceState::Canceled => {
+                return MockResponse::lnd_error(500, 2, "invoice already canceled")
+            }
+            LndInvoiceState::Open => return MockResponse::lnd_error(500, 2, "invoice still open"),
+        }
+        self.notify(&hash);
+        MockResponse::ok("{}")
+    }
+    fn cancel_invoice(&mut self, body: &Value) -> MockResponse {
+        let Some(hash) =
+            json_bytes(body, "payment_hash").and_then(|h| <[u8; 32]>::try_from(h).ok())
+        else {
+            return MockResponse::lnd_error(500, 2, "invalid hash");
+        };
+        let Some(invoice) = self.invoice_mut(&hash) else {
+            return MockResponse::lnd_error(404, 5, "unable to locate invoice");
+        };
+        if invoice.state == LndInvoiceState::Settled {
+            return MockResponse::lnd_error(500, 2, "invoice already settled");
+        }
+        invoice.state = LndInvoiceState::Canceled;
+        invoice.htlcs.clear();
+        invoice.amt_paid_msat = 0;
+        self.notify(&hash);
+        MockResponse::ok("{}")
+    }
+    fn lookup_invoice(&self, hash: Option<[u8; 32]>, addr: Option<[u8; 32]>) -> MockResponse {
+        let invoice = self
+            .invoices
+            .iter()
+            .find(|i| Some(i.r_hash) == hash || Some(i.payment_addr) == addr);
+        match invoice {
+            Some(invoice) => MockResponse::json(&invoice.to_json()),
+            None => MockResponse::lnd_error(404, 5, "there are no existing invoices"),
+        }
+    }
+    fn list_invoices(&self, request: &MockRequest) -> MockResponse {
+        let param = |key: &str| {
+            request
+                .query_param(key)
+                .and_then(|value| value.parse::<u64>().ok())
+                .unwrap_or_default()
+        };
+        let flag = |key: &str| {
+            request
+                .query_param(key)
+                .is_some_and(|value| value == "true")
+        };
+        let offset = param("index_offset");
+        let max = match param("num_max_invoices") {
+            0 => 100,
+            max => max as usize,
+        };
+        let (start, end) = (param("creation_date_start"), param("creation_date_end"));
+        let pending_only = flag("pending_only");
+        let matching = self.invoices.iter().filter(|i| {
+            (!pending_only || matches!(i.state, LndInvoiceState::Open | LndInvoiceState::Accepted))
+                && i.creation_date >= start
+                && (end == 0 || i.creation_date <= end)
+        });
+        let page = match flag("reversed") {
+            true => {
+                let mut page = matching
+                    .filter(|i| offset == 0 || i.add_index < offset)
+                    .collect::<Vec<_>>();
+                page.drain(..page.len().saturating_sub(max));
+                page
+            }
+            false => matching
+                .filter(|i| i.add_index > offset)
+                .take(max)
+                .collect(),
+        };
+        let first = page.first().map(|i| i.add_index).unwrap_or_default();
+        let last = page.last().map(|i| i.add_index).unwrap_or_default();
+        let invoices = page
+            .into_iter()
+            .map(MockInvoice::to_json)
+            .collect::<Vec<_>>();
+        MockResponse::json(&json!({
+            "invoices": invoices,
+            "last_index_offset": last.to_string(),
+            "first_index_offset": first.to_string(),
+        }))
+    }
+    fn list_payments(&self, request: &MockRequest) -> MockResponse {
+        let param = |key: &str| {
+            request
+                .query_param(key)
+                .and_then(|value| value.parse::<u64>().ok())
+                .unwrap_or_default()
+        };
+        let flag = |key: &str| {
+            request
+                .query_param(key)
+                .is_some_and(|value| value == "true")
+        };
+        let index =
+            |payment: &MockPayment| json_u64(&payment.base, "payment_index").unwrap_or_default();
+        let created =
+            |payment: &MockPayment| json_u64(&payment.base, "creation_date").unwrap_or_default();
+        let offset = param("index_offset");
+        let max = match param("max_payments") {
+            0 => 100,
+            max => max as usize,
+        };
+        let (start, end) = (param("creation_date_start"), param("creation_date_end"));
+        let include_incomplete = flag("include_incomplete");
+        let mut matching = self
+            .payments
+            .iter()
+            .filter(|p| include_incomplete || p.latest["status"] == "SUCCEEDED")
+            .filter(|p| created(p) >= start && (end == 0 || created(p) <= end))
+            .collect::<Vec<_>>();
+        matching.sort_by_key(|p| index(p));
+        let total = matching.len();
+        let page = match flag("reversed") {
+            true => {
+                let mut page = matching
+                    .into_iter()
+                    .filter(|p| offset == 0 || index(p) < offset)
+                    .collect::<Vec<_>>();
+                page.drain(..page.len().saturating_sub(max));
+                page
+            }
+            false => matching
+                .into_iter()
+                .filter(|p| index(p) > offset)
+                .take(max)
+                .collect(),
+        };
+        let first = page.first().map(|p| index(p)).unwrap_or_default();
+        let last = page.last().map(|p| index(p)).unwrap_or_default();
+        let total = if flag("count_total_payments") {
+            total
+        } else {
+            0
+        };
+        let payments = page
+            .into_iter()
+            .map(|p| p.latest.clone())
+            .collect::<Vec<_>>();
+        MockResponse::json(&json!({
+            "payments": payments,
+            "first_index_offset": first.to_string(),
+            "last_index_offset": last.to_string(),
+            "total_num_payments": total.to_string(),
+        }))
+    }
+    fn next_address(&mut self, body: &Value) -> MockResponse {
+        let account = match body["account"].as_str().unwrap_or_default() {
+            "" => "default",
+            account => account,
+        }
+        .to_string();
+        let address_type = body["type"]
+            .as_str()
+            .unwrap_or("TAPROOT_PUBKEY")
+            .to_string();
+        let prefix = match address_type.as_str() {
+            "TAPROOT_PUBKEY" => "bcrt1p",
+            _ => "bcrt1q",
+        };
+        self.counter += 1;
+        let address = format!("{}mock{:04}", prefix, self.counter);
+        self.addresses
+            .push((account, address_type, address.clone()));
+        MockResponse::json(&json!({ "addr": address }))
+    }
+    fn list_addresses(&self) -> MockResponse {
+        let mut accounts: Vec<Value> = vec![];
+        for (account, address_type, address) in &self.addresses {
+            let entry = json!({
+                "address": address,
+                "is_internal": false,
+                "balance": "0",
+                "derivation_path": "m/86'/0'/0'/0/0",
+                "public_key": hex(&Sha256::digest(address.as_bytes())),
+            });
+            match accounts
+                .iter_mut()
+                .find(|a| a["name"] == *account && a["address_type"] == *address_type)
+            {
+                Some(existing) => existing["addresses"].as_array_mut().unwrap().push(entry),
+                None => accounts.push(json!({
+                    "name": account,
+                    "address_type": address_type,
+                    "derivation_path": "m/86'/0'/0'",
+                    "addresses": [entry],
+                })),
+            }
+        }
+        MockResponse::json(&json!({ "account_with_addresses": accounts }))
+    }
+    fn get_info(&self) -> MockResponse {
+        let pubkey = node_pubkey(&NODE_KEY);
+        MockResponse::json(&json!({
+            "version": "0.18.0-beta commit=mock",
+            "commit_hash": "0000000000000000000000000000000000000000",
+            "identity_pubkey": pubkey,
+            "alias": "bright-mock",
+            "color": "#3399ff",
+            "num_pending_channels": 0,
+            "num_active_channels": 1,
+            "num_inactive_channels": 0,
+            "num_peers": 1,
+            "block_height": self.block_height,
+            "block_hash": "0000000000000000000000000000000000000000000000000000000000000000",
+            "best_header_timestamp": now().to_string(),
+            "synced_to_chain": true,
+            "synced_to_graph": true,
+            "testnet": false,
+            "chains": [{ "chain": "bitcoin", "network": "regtest" }],
+            "uris": [format!("{}@127.0.0.1:9735", pubkey)],
+            "features": {
+                "0": { "name": "data-loss-protect", "is_required": true, "is_known": true },
+                "9": { "name": "tlv-onion", "is_required": false, "is_known": true },
+            },
+            "require_htlc_interceptor": false,
+            "store_final_htlc_resolutions": false,
+        }))
+    }
+    fn channel_balance(&self) -> MockResponse {
+        let amount = |sat: u64| json!({ "sat": sat.to_string(), "msat": (sat * 1000).to_string() });
+        MockResponse::json(&json!({
+            "balance": "500000",
+            "pending_open_balance": "0",
+            "local_balance": amount(500_000),
+            "remote_balance": amount(250_000),
+            "unsettled_local_balance": amount(0),
+            "unsettled_remote_balance": amount(0),
+            "pending_open_local_balance": amount(0),
+            "pending_open_remote_balance": amount(0),
+        }))
+    }
+    fn wallet_balance(&self) -> MockResponse {
+        MockResponse::json(&json!({
+            "total_balance": "150000",
+            "confirmed_balance": "100000",
+            "unconfirmed_balance": "50000",
+            "locked_balance": "0",
+            "reserved_balance_anchor_chan": "10000",
+            "account_balance": {
+                "default": { "confirmed_balance": "100000", "unconfirmed_balance": "50000" },
+            },
+        }))
+    }
+    fn lnurl(&mut self, request: &MockRequest) -> MockResponse {
+        if let Some(user) = request.path.strip_prefix("/.well-known/lnurlp/") {
+            return MockResponse::json(&json!({
+                "callback": format!("https://localhost:{}/lnurlp/{}/callback", self.port, user),
+                "minSendable": 1000,
+                "maxSendable": 100_000_000_000u64,
+                "metadata": format!("[[\"text/plain\",\"Pay {}\"]]", user),
+                "tag": "payRequest",
+            }));
+        }
+        let Some(amount) = request
+            .query_param("amount")
+            .and_then(|amount| amount.parse::<u64>().ok())
+        else {
+            return MockResponse::json(&json!({ "status": "ERROR", "reason": "Missing amount" }));
+        };
+        let payment_request = self.remote_invoice(amount, "LNURL payment");
+        MockResponse::json(&json!({ "pr": payment_request, "routes": [] }))
+    }
+    fn remote_invoice(&mut self, amount_msat: u64, memo: &str) -> String {
+        let preimage = self.next_bytes("remote_preimage");
+        let hash: [u8; 32] = Sha256::digest(preimage).into();
+        let payment_addr = self.next_bytes("remote_payment_addr");
+        self.remote_invoices
+            .insert(hash, RemoteInvoice { preimage });
+        bolt11(
+            &REMOTE_KEY,
+            hash,
+            payment_addr,
+            amount_msat,
+            memo,
+            DEFAULT_EXPIRY,
+            DEFAULT_CLTV_EXPIRY,
+        )
+    }
+    fn invoice_payment(&self, payment_request: &str, request: &Value) -> Option<MockSend> {
+        let invoice = payment_request.parse::<Bolt11Invoice>().ok()?;
+        let hash = invoice.payment_hash().to_byte_array();
+        let preimage = match self.remote_invoices.get(&hash) {
+            Some(remote) => Some(remote.preimage),
+            None => self
+                .invoices
+                .iter()
+                .find(|i| i.r_hash == hash)
+                .and_then(|i| i.r_preimage),
+        };
+        let value_msat = invoice
+            .amount_milli_satoshis()
+            .or_else(|| json_u64(request, "amt_msat"))
+            .or_else(|| json_u64(request, "amt").map(|amt| amt * 1000))
+            .unwrap_or_default();
+        Some(MockSend {
+            hash,
+            preimage,
+            value_msat,
+            is_remote: self.remote_invoices.contains_key(&hash),
+            payee: invoice.recover_payee_pub_key().to_string(),
+            final_cltv_delta: invoice.min_final_cltv_expiry_delta(),
+            payment_addr: Some(invoice.payment_secret().0),
+            custom_records: json!({}),
+        })
+    }
+    /// Keysend payments carry their preimage in a custom record, any node accepts
+    /// them as long as it matches the payment hash.
+    fn keysend_payment(&self, request: &Value) -> Option<MockSend> {
+        let dest = json_bytes(request, "dest").filter(|dest| dest.len() == 33)?;
+        let hash = request["payment_hash"].as_str().and_then(decode_hash)?;
+        let preimage = request["dest_custom_records"][KEYSEND_RECORD]
+            .as_str()
+            .and_then(decode_hash)
+            .filter(|preimage| <[u8; 32]>::from(Sha256::digest(preimage)) == hash);
+        let payee = hex(&dest);
+        Some(MockSend {
+            hash,
+            preimage,
+            value_msat: json_u64(request, "amt_msat")
+                .or_else(|| json_u64(request, "amt").map(|amt| amt * 1000))
+                .unwrap_or_default(),
+            is_remote: payee != node_pubkey(&NODE_KEY),
+            payee,
+            final_cltv_delta: DEFAULT_CLTV_EXPIRY,
+            payment_addr: None,
+            custom_records: request["dest_custom_records"].clone(),
+        })
+    }
+    /// Resolves a payment sent through the router into its status updates.
+    fn send_payment(&mut self, request: &Value) -> Vec<Value> {
+        let payment_request = request["payment_request"].as_str().unwrap_or_default();
+        let payment = if payment_request.is_empty() {
+            self.keysend_payment(request)
+        } else {
+            self.invoice_payment(payment_request, request)
+        };
+        let Some(payment) = payment else {
+            return vec![json!({ "error": { "code": 2, "message": "invalid payment request" } })];
+        };
+        let MockSend {
+            hash,
+            preimage,
+            value_msat,
+            is_remote,
+            ..
+        } = payment;
+        // Remote payments pay a flat routing fee of 1 sat plus 1000 ppm.
+        let fee_msat = if is_remote {
+            1000 + value_msat / 1000
+        } else {
+            0
+        };
+        let fee_limit_msat = json_u64(request, "fee_limit_msat")
+            .or_else(|| json_u64(request, "fee_limit_sat").map(|sat| sat * 1000))
+            .unwrap_or_default();
+        let updates = match self.payment_scripts.remove(payment_request) {
+            Some(updates) => updates,
+            None if fee_msat > fee_limit_msat => vec![
+                MockPaymentUpdate::InFlight,
+                MockPaymentUpdate::Failed("FAILURE_REASON_NO_ROUTE".to_string()),
+            ],
+            None if preimage.is_some() => {
+                vec![MockPaymentUpdate::InFlight, MockPaymentUpdate::Succeeded]
+            }
+            None => vec![
+                MockPaymentUpdate::InFlight,
+                MockPaymentUpdate::Failed("FAILURE_REASON_INCORRECT_PAYMENT_DETAILS".to_string()),
+            ],
+        };
+        if !is_remote
+            && !payment_request.is_empty()
+            && updates.contains(&MockPaymentUpdate::Succeeded)
+        {
+            // Paying one of our own invoices, LND would route it back to us.
+            let _ = self.simulate_payment(&hash);
+        }
+        let final_expiry = self.block_height as u64 + payment.final_cltv_delta;
+        let final_hop = json!({
+            "chan_id": MOCK_CHAN_ID.to_string(),
+            "expiry": final_expiry,
+            "amt_to_forward_msat": value_msat.to_string(),
+            "fee_msat": "0",
+            "pub_key": payment.payee,
+            "tlv_payload": true,
+            "mpp_record": payment.payment_addr.map(|payment_addr| json!({
+                "payment_addr": BASE64_STANDARD.encode(payment_addr),
+                "total_amt_msat": value_msat.to_string(),
+            })),
+            "custom_records": payment.custom_records,
+        });
+        let hops = if is_remote {
+            let forward_delta = 40;
+            vec![
+                json!({
+                    "chan_id": MOCK_CHAN_ID.to_string(),
+                    "expiry": final_expiry + forward_delta,
+                    "amt_to_forward_msat": value_msat.to_string(),
+                    "fee_msat": fee_msat.to_string(),
+                    "pub_key": node_pubkey(&ROUTING_KEY),
+                    "tlv_payload": true,
+                }),
+                final_hop,
+            ]
+        } else {
+            vec![final_hop]
+        };
+        let route = json!({
+            "total_time_lock": hops[0]["expiry"],
+            "total_fees_msat": fee_msat.to_string(),
+            "total_amt_msat": (value_msat + fee_msat).to_string(),
+            "hops": hops,
+        });
+        self.counter += 1;
+        let creation_time_ns = now() * 1_000_000_000;
+        let payment = MockPayment {
+            hash,
+            preimage,
+            hops,
+            route,
+            base: json!({
+                "payment_hash": hex(&hash),
+                "value": (value_msat / 1000).to_string(),
+                "creation_date": now().to_string(),
+                "fee": (fee_msat / 1000).to_string(),
+                "value_sat": (value_msat / 1000).to_string(),
+                "value_msat": value_msat.to_string(),
+                "payment_request": payment_request,
+                "fee_sat": (fee_msat / 1000).to_string(),
+                "fee_msat": fee_msat.to_string(),
+                "creation_time_ns": creation_time_ns.to_string(),
+                "payment_index": self.counter.to_string(),
+            }),
+            latest: Value::Null,
+        };
+        self.payments.retain(|existing| existing.hash != hash);
+        self.payments.push(payment);
+        updates
+            .iter()
+            .filter_map(|update| self.update_payment(&hash, update))
+            .map(|payment| json!({ "result": payment }))
+            .collect()
+    }
+    /// Moves a payment to the state of `update`, notifying its trackers.
+    fn update_payment(&mut self, hash: &[u8; 32], update: &MockPaymentUpdate) -> Option<Value> {
+        let payment = self.payments.iter_mut().find(|p| &p.hash == hash)?;
+        payment.latest = payment.to_json(update);
+        let latest = payment.latest.clone();
+        self.payment_subscribers.retain(|(subscribed, sender)| {
+            subscribed.is_some_and(|subscribed| &subscribed != hash)
+                || sender.send(latest.clone()).is_ok()
+        });
+        Some(latest)
+    }
+}
+
+#[derive(Clone)]
+struct MockLndHandler(Arc<Mutex<MockLndState>>);
+impl MockHandler for MockLndHandler {
+    fn http(&self, request: MockRequest) -> MockResponse {
+        let mut state = self.0.lock().unwrap();
+        if request.path.starts_with("/.well-known/lnurlp/") || request.path.starts_with("/lnurlp/")
+        {
+            return state.lnurl(&request);
+        }
+        if !authorized(&request) {
+            return MockResponse::lnd_error(401, 16, "verification failed: signature mismatch");
+        }
+        let body = serde_json::from_str::<Value>(&request.body).unwrap_or(Value::Null);
+        match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/v1/getinfo") => state.get_info(),
+            ("GET", "/v1/balance/channels") => state.channel_balance(),
+            ("GET", "/v1/balance/blockchain") => state.wallet_balance(),
+            ("POST", "/v1/invoices") => state.add_invoice(&body, None),
+            ("GET", "/v1/invoices") => state.list_invoices(&request),
+            ("GET", "/v1/payments") => state.list_payments(&request),
+            ("POST", "/v2/invoices/hodl") => {
+                match json_bytes(&body, "hash").and_then(|h| <[u8; 32]>::try_from(h).ok()) {
+                    Some(hash) => state.add_invoice(&body, Some(hash)),
+                    None => MockResponse::lnd_error(500, 2, "invalid hash"),
+                }
+            }
+            ("POST", "/v2/invoices/settle") => state.settle_invoice(&body),
+            ("POST", "/v2/invoices/cancel") => state.cancel_invoice(&body),
+            ("GET", "/v2/invoices/lookup") => state.lookup_invoice(
+                request
+                    .query_param("payment_hash")
+                    .and_then(|h| decode_hash(&h)),
+                request
+                    .query_param("payment_addr")
+                    .and_then(|a| decode_hash(&a)),
+            ),
+            ("GET", path) if path.starts_with("/v1/invoice/") => {
+                let hash = path.trim_start_matches("/v1/invoice/");
+                state.lookup_invoice(decode_hash(hash).filter(|_| hash.len() == 64), None)
+            }
+            ("GET", path) if path.starts_with("/v1/payreq/") => {
+                decode_pay_req(path.trim_start_matches("/v1/payreq/"))
+            }
+            ("POST", "/v2/wallet/address/next") => state.next_address(&body),
+            ("GET", "/v2/wallet/addresses") => state.list_addresses(),
+            _ => MockResponse::lnd_error(404, 12, "Not Found"),
+        }
+    }
+    fn websocket(&self, request: MockRequest, socket: MockWebsocket) -> BoxFuture<'static, ()> {
+        let state = self.0.clone();
+        Box::pin(async move {
+            let mut socket = socket;
+            if !authorized(&request) {
+                let error = json!({ "error": { "code": 16, "message": "verification failed" } });
+                let _ = socket.send(Message::Text(error.to_string().into())).await;
+                let _ = socket.close(None).await;
+                return;
+            }
+            if request.path == "/v2/router/send" {
+                router_send(state, socket).await;
+            } else if request.path == "/v2/router/payments" {
+                let (sender, receiver) = mpsc::unbounded_channel();
+                {
+                    let mut state = state.lock().unwrap();
+                    state.payment_subscribers.push((None, sender));
+                    state.payment_tracker_connected.notify_waiters();
+                }
+                forward_updates(socket, receiver).await;
+            } else if let Some(hash) = request.path.strip_prefix("/v2/router/track/") {
+                track_payment(state, decode_hash(hash), socket).await;
+            } else if request.path == "/v1/invoices/subscribe" {
+                let index = |key: &str| {
+                    request
+                        .query_param(key)
+                        .and_then(|value| value.parse::<u64>().ok())
+                        .unwrap_or_default()
+                };
+                let (add_index, settle_index) = (index("add_index"), index("settle_index"));
+                subscribe_all_invoices(state, add_index, settle_index, socket).await;
+            } else if let Some(hash) = request.path.strip_prefix("/v2/invoices/subscribe/") {
+                subscribe_single_invoice(state, decode_hash(hash), socket).await;
+            } else {
+                let _ = socket.close(None).await;
+            }
+        })
+    }
+}
+
+async fn router_send(state: Arc<Mutex<MockLndState>>, mut socket: MockWebsocket) {
+    while let Some(Ok(message)) = socket.next().await {
+        let Message::Text(text) = message else {
+            continue;
+        };
+        let request = serde_json::from_str::<Value>(text.as_str()).unwrap_or(Value::Null);
+        let updates = state.lock().unwrap().send_payment(&request);
+        for update in updates {
+            if socket
+                .send(Message::Text(update.to_string().into()))
+                .await
+                .is_err()
+            {
+                return;
+            }
+        }
+    }
+}
+
+async fn forward_updates(mut socket: MockWebsocket, mut updates: mpsc::UnboundedReceiver<Value>) {
+    loop {
+        tokio::select! {
+            update = updates.recv() => {
+                let Some(update) = update else {
+                    break;
+                };
+                let message = json!({ "result": update }).to_string();
+                if socket.send(Message::Text(message.into())).await.is_err() {
+                    return;
+                }
+            }
+            message = socket.next() => {
+                match message {
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                    _ => {}
+                }
+            }
+        }
+    }
+    let _ = socket.close(None).await;
+}
+
+/// Replays the invoices added after `add_index` and settled after `settle_index`,
+/// zero meaning no replay, then forwards new events.
+async fn subscribe_all_invoices(
+    state: Arc<Mutex<MockLndState>>,
+    add_index: u64,
+    settle_index: u64,
+    socket: MockWebsocket,
+) {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    {
+        let mut state = state.lock().unwrap();
+        if add_index > 0 {
+            for invoice in state.invoices.iter().filter(|i| i.add_index > add_index) {
+                let _ = sender.send(invoice.to_json());
+            }
+        }
+        if settle_index > 0 {
+            let mut settled = state
+                .invoices
+                .iter()
+                .filter(|i| i.settle_index > settle_index)
+                .collect::<Vec<_>>();
+            settled.sort_by_key(|i| i.settle_index);
+            for invoice in settled {
+                let _ = sender.send(invoice.to_json());
+            }
+        }
+        state.all_invoices_subscribers.push(sender);
+    }
+    forward_updates(socket, receiver).await;
+}
+
+async fn subscribe_single_invoice(
+    state: Arc<Mutex<MockLndState>>,
+    hash: Option<[u8; 32]>,
+    mut socket: MockWebsocket,
+) {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let found = {
+        let mut state = state.lock().unwrap();
+        let invoice = hash.and_then(|hash| state.invoices.iter().find(|i| i.r_hash == hash));
+        match invoice.map(|invoice| (invoice.r_hash, invoice.to_json())) {
+            Some((hash, invoice)) => {
+                let _ = sender.send(invoice);
+                state.invoice_subscribers.push((hash, sender));
+                true
+            }
+            None => false,
+        }
+    };
+    if !found {
+        let error = json!({ "error": { "code": 5, "message": "unable to locate invoice" } });
+        let _ = socket.send(Message::Text(error.to_string().into())).await;
+        let _ = socket.close(None).await;
+        return;
+    }
+    forward_updates(socket, receiver).await;
+}
+
+/// Sends the current state of a payment, then its updates until it is final.
+async fn track_payment(
+    state: Arc<Mutex<MockLndState>>,
+    hash: Option<[u8; 32]>,
+    mut socket: MockWebsocket,
+) {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let found = {
+        let mut state = state.lock().unwrap();
+        let payment = hash.and_then(|hash| state.payments.iter().find(|p| p.hash == hash));
+        match payment.map(|p| (p.hash, p.latest.clone(), p.is_final())) {
+            Some((hash, latest, is_final)) => {
+                let _ = sender.send(latest);
+                if !is_final {
+                    state.payment_subscribers.push((Some(hash), sender));
+                }
+                true
+            }
+            None => false,
+        }
+    };
+    if !found {
+        let error = json!({ "error": { "code": 5, "message": "payment isn't initiated" } });
+        let _ = socket.send(Message::Text(error.to_string().into())).await;
+        let _ = socket.close(None).await;
+        return;
+    }
+    forward_updates(socket, receiver).await;
+}
+
+/// In-process LND node serving the REST and websocket endpoints used by the crate,
+/// plus an LNURL-pay service for [`crate::LightningAddress`].
+///
+/// Invoices, hodl invoices and payments move through LND's states as driven by the
+/// test, so flows can be exercised without a real node or network access.
+pub struct MockLnd {
+    server: MockServer,
+    state: Arc<Mutex<MockLndState>>,
+}
+impl MockLnd {
+    pub async fn start() -> Self {
+        let state = Arc::new(Mutex::new(MockLndState {
+            block_height: 800_000,
+            addresses: vec![
+                (
+                    "default".to_string(),
+                    "TAPROOT_PUBKEY".to_string(),
+                    "bcrt1pmock0000".to_string(),
+                ),
+                (
+                    "default".to_string(),
+                    "WITNESS_PUBKEY_HASH".to_string(),
+                    "bcrt1qmock0000".to_string(),
+                ),
+            ],
+            ..Default::default()
+        }));
+        let server = MockServer::start(MockLndHandler(state.clone())).await;
+        state.lock().unwrap().port = server.port;
+        Self { server, state }
+    }
+    pub fn port(&self) -> u16 {
+        self.server.port
+    }
+    pub fn tls(&self) -> LndTlsConfig {
+        self.server.tls()
+    }
+    /// Client pinned to the mock certificate and authenticated with [`MOCK_MACAROON`].
+    pub fn client(&self) -> LightningClient {
+        self.server.client()
+    }
+    /// Lightning address served by the mock LNURL-pay endpoints.
+    pub fn lightning_address(&self, user: &str) -> String {
+        format!("{}@localhost:{}", user, self.port())
+    }
+    pub fn block_height(&self) -> u32 {
+        self.state.lock().unwrap().block_height
+    }
+    pub fn set_block_height(&self, height: u32) {
+        self.state.lock().unwrap().block_height = height;
+    }
+    pub fn mine_blocks(&self, blocks: u32) {
+        self.state.lock().unwrap().block_height += blocks;
+    }
+    /// Creates an invoice on a remote node that the router can pay.
+    pub fn remote_invoice(&self, amount_msat: u64, memo: &str) -> String {
+        self.state.lock().unwrap().remote_invoice(amount_msat, memo)
+    }
+    /// Simulates an incoming payment for one of the node's invoices. Regular
+    /// invoices settle, hodl invoices move to `ACCEPTED`.
+    pub fn simulate_payment(&self, payment_hash: &PaymentHash) -> BrightResult<()> {
+        self.state
+            .lock()
+            .unwrap()
+            .simulate_payment(payment_hash.as_bytes())
+    }
+    /// Forces an invoice into a state and notifies its subscribers.
+    pub fn set_invoice_state(
+        &self,
+        payment_hash: &PaymentHash,
+        state: LndInvoiceState,
+    ) -> BrightResult<()> {
+        let hash = *payment_hash.as_bytes();
+        let mut mock = self.state.lock().unwrap();
+        let settle_index = mock.next_settle_index();
+        let invoice = mock
+            .invoice_mut(&hash)
+            .ok_or_else(|| BrightError::invalid_input("Unknown invoice"))?;
+        match state {
+            LndInvoiceState::Settled => settle(invoice, settle_index),
+            state => invoice.state = state,
+        }
+        mock.notify(&hash);
+        Ok(())
+    }
+    /// Closes every invoice subscription, as a node restart would.
+    pub fn drop_subscriptions(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.invoice_subscribers.clear();
+        state.all_invoices_subscribers.clear();
+    }
+    /// Waits until a tracker of all payments is connected, so the payments made
+    /// afterwards are reported to it.
+    pub async fn payment_tracker_connected(&self) {
+        let notify = self.state.lock().unwrap().payment_tracker_connected.clone();
+        loop {
+            let mut notified = std::pin::pin!(notify.notified());
+            notified.as_mut().enable();
+            let connected = self
+                .state
+                .lock()
+                .unwrap()
+                .payment_subscribers
+                .iter()
+                .any(|(hash, _)| hash.is_none());
+            if connected {
+                return;
+            }
+            notified.await;
+        }
+    }
+    pub fn invoice_state(&self, payment_hash: &PaymentHash) -> Option<LndInvoiceState> {
+        let hash = *payment_hash.as_bytes();
+        self.state
+            .lock()
+            .unwrap()
+            .invoices
+            .iter()
+            .find(|i| i.r_hash == hash)
+            .map(|i| i.state.clone())
+    }
+    /// Moves a payment sent through the router to a new state, as its HTLCs resolve.
+    pub fn resolve_payment(
+        &self,
+        payment_hash: &PaymentHash,
+        update: MockPaymentUpdate,
+    ) -> BrightResult<()> {
+        self.state
+            .lock()
+            .unwrap()
+            .update_payment(payment_hash.as_bytes(), &update)
+            .map(|_| ())
+            .ok_or_else(|| BrightError::invalid_input("Unknown payment"))
+    }
+    /// Replaces the updates the router sends for the next payment of `payment_request`.
+    pub fn script_payment(&self, payment_request: &str, updates: Vec<MockPaymentUpdate>) {
+        self.state
+            .lock()
+            .unwrap()
+            .payment_scripts
+            .insert(payment_request.to_string(), updates);
+    }
+}
+
+fn decode_pay_req(pay_req: &str) -> MockResponse {
+    let invoice = match pay_req.parse::<Bolt11Invoice>() {
+        Ok(invoice) => invoice,
+        Err(e) => return MockResponse::lnd_error(500, 2, &e.to_string()),
+    };
+    let (description, description_hash) = match invoice.description() {
+        Bolt11InvoiceDescription::Direct(memo) => (memo.to_string(), String::new()),
+        Bolt11InvoiceDescription::Hash(hash) => (String::new(), hex(&hash.0[..])),
+    };
+    let amount_msat = invoice.amount_milli_satoshis().unwrap_or_default();
+    MockResponse::json(&json!({
+        "destination": invoice.get_payee_pub_key().to_string(),
+        "payment_hash": hex(&invoice.payment_hash()[..]),
+        "num_satoshis": (amount_msat / 1000).to_string(),
+        "timestamp": invoice.duration_since_epoch().as_secs().to_string(),
+        "expiry": invoice.expiry_time().as_secs().to_string(),
+        "description": description,
+        "description_hash": description_hash,
+        "fallback_addr": "",
+        "cltv_expiry": invoice.min_final_cltv_expiry_delta().to_string(),
+        "route_hints": [],
+        "payment_addr": BASE64_STANDARD.encode(invoice.payment_secret().0),
+        "num_msat": amount_msat.to_string(),
+        "features": {
+            "9": { "name": "tlv-onion", "is_required": false, "is_known": true },
+            "14": { "name": "payment-addr", "is_required": true, "is_known": true },
+        },
+    }))
+}
+
+fn settle(invoice: &mut MockInvoice, settle_index: u64) {
+    invoice.state = LndInvoiceState::Settled;
+    invoice.settle_index = settle_index;
+    invoice.settle_date = now();
+    invoice.amt_paid_msat = invoice.value_msat;
+    for htlc in invoice.htlcs.iter_mut() {
+        htlc["state"] = json!("SETTLED");
+        htlc["resolve_time"] = json!(now().to_string());
+    }
+}
+
+fn authorized(request: &MockRequest) -> bool {
+    request
+        .headers
+        .get("grpc-metadata-macaroon")
+        .is_some_and(|macaroon| macaroon == MOCK_MACAROON)
+}
+
+/// Regtest payment request for `payment_hash` signed by the mock node, for
+/// fixtures that need a BOLT11 that decodes.
+pub fn mock_bolt11(payment_hash: &PaymentHash, amount_msat: u64, memo: &str) -> String {
+    bolt11(
+        &NODE_KEY,
+        *payment_hash.as_bytes(),
+        [0x33; 32],
+        amount_msat,
+        memo,
+        DEFAULT_EXPIRY,
+        DEFAULT_CLTV_EXPIRY,
+    )
+}
+
+fn bolt11(
+    key: &[u8; 32],
+    hash: [u8; 32],
+    payment_addr: [u8; 32],
+    amount_msat: u64,
+    memo: &str,
+    expiry: u64,
+    cltv_expiry: u64,
+) -> String {
+    let secp = Secp256k1::new();
+    let key = SecretKey::from_slice(key).unwrap();
+    let mut builder = InvoiceBuilder::new(Currency::Regtest)
+        .description(memo.to_string())
+        .payment_hash(sha256::Hash::from_byte_array(hash))
+        .payment_secret(PaymentSecret(payment_addr))
+        .duration_since_epoch(Duration::from_secs(now()))
+        .min_final_cltv_expiry_delta(cltv_expiry)
+        .expiry_time(Duration::from_secs(expiry));
+    if amount_msat > 0 {
+        builder = builder.amount_milli_satoshis(amount_msat);
+    }
+    builder
+        .build_signed(|message| secp.sign_ecdsa_recoverable(message, &key))
+        .unwrap()
+        .to_string()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn node_pubkey(key: &[u8; 32]) -> String {
+    let secp = Secp256k1::new();
+    SecretKey::from_slice(key)
+        .unwrap()
+        .public_key(&secp)
+        .to_string()
+}
+
+/// Failure of the last attempt of a payment that failed for `reason`.
+fn htlc_failure(reason: &str, hops: &[Value]) -> Value {
+    let (code, failure_source_index) = match reason {
+        "FAILURE_REASON_INCORRECT_PAYMENT_DETAILS" => {
+            ("INCORRECT_OR_UNKNOWN_PAYMENT_DETAILS", hops.len())
+        }
+        _ => ("TEMPORARY_CHANNEL_FAILURE", 1),
+    };
+    json!({
+        "code": code,
+        "htlc_msat": "0",
+        "cltv_expiry": 0,
+        "flags": 0,
+        "failure_source_index": failure_source_index,
+        "height": 0,
+    })
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn decode_bytes(value: &str) -> Option<Vec<u8>> {
+    if value.len() == 64 && value.chars().all(|c| c.is_ascii_hexdigit()) {
+        return (0..value.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&value[i..i + 2], 16).ok())
+            .collect();
+    }
+    BASE64_STANDARD
+        .decode(value)
+        .or_else(|_| BASE64_URL_SAFE.decode(value))
+        .or_else(|_| BASE64_URL_SAFE_NO_PAD.decode(value))
+        .or_else(|_| BASE64_STANDARD_NO_PAD.decode(value))
+        .ok()
+}
+
+fn decode_hash(value: &str) -> Option<[u8; 32]> {
+    decode_bytes(value).and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+}
+
+fn json_bytes(body: &Value, key: &str) -> Option<Vec<u8>> {
+    body[key]
+        .as_str()
+        .filter(|value| !value.is_empty())
+        .and_then(decode_bytes)
+}
+
+fn json_u64(body: &Value, key: &str) -> Option<u64> {
+    match &body[key] {
+        Value::String(value) => value.parse().ok(),
+        Value::Number(value) => value.as_u64(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{MockLnd, MockPaymentUpdate};
+    use crate::{
+        InvoicePaymentState, LndInvoiceRequestBody, LndInvoiceState, LndPaymentRequest,
+        LndPaymentResponse, LndWebsocketMessage,
+    };
+
+    #[tokio::test]
+    async fn scripted_payment_fails() -> anyhow::Result<()> {
+        let lnd = MockLnd::start().await;
+        let client = lnd.client();
+        let pay_request = lnd.remote_invoice(5000, "Scripted");
+        lnd.script_payment(
+            &pay_request,
+            vec![
+                MockPaymentUpdate::InFlight,
+                MockPaymentUpdate::Failed("FAILURE_REASON_NO_ROUTE".to_string()),
+            ],
+        );
+        let lnd_ws = client.invoice_channel().await?;
+        lnd_ws
+            .sender
+            .send(LndPaymentRequest::new(pay_request, 10, 10, false))
+            .await?;
+        let mut statuses = vec![];
+        while let Some(LndWebsocketMessage::Response(state)) =
+            lnd_ws.receiver.read::<LndPaymentResponse>().await
+        {
+            statuses.push(state.status());
+            if state.status() == InvoicePaymentState::Failed {
+                break;
+            }
+        }
+        assert_eq!(
+            statuses,
+            vec![InvoicePaymentState::InFlight, InvoicePaymentState::Failed]
+        );
+        Ok(())
+    }
+    #[tokio::test]
+    async fn simulated_payment_settles_invoice() -> anyhow::Result<()> {
+        let lnd = MockLnd::start().await;
+        let client = lnd.client();
+        let invoice = client
+            .get_invoice(LndInvoiceRequestBody::new(1000, None))
+            .await?;
+        assert_eq!(
+            lnd.invoice_state(&invoice.r_hash),
+            Some(LndInvoiceState::Open)
+        );
+        lnd.simulate_payment(&invoice.r_hash)?;
+        assert_eq!(
+            lnd.invoice_state(&invoice.r_hash),
+            Some(LndInvoiceState::Settled)
+        );
+        assert!(lnd.simulate_payment(&invoice.r_hash).is_err());
+        Ok(())
+    }
+}
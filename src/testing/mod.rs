@@ -0,0 +1,5 @@
+//! In-process mock LND for tests, enabled with the `testing` feature.
+mod mock_lnd;
+mod server;
+pub use mock_lnd::*;
+pub use server::*;
@@ -0,0 +1,237 @@
+use std::{collections::HashMap, sync::Arc};
+
+use futures_util::future::BoxFuture;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tokio_tungstenite::{
+    tungstenite::{handshake::derive_accept_key, protocol::Role},
+    WebSocketStream,
+};
+
+use crate::{LightningClient, LndTlsConfig};
+
+/// Macaroon expected by the mock servers, hex encoded.
+pub const MOCK_MACAROON: &str = "0201036c6e64";
+
+pub type MockWebsocket = WebSocketStream<tokio_native_tls::TlsStream<TcpStream>>;
+
+/// Self-signed certificate valid for `localhost` and `127.0.0.1`.
+pub struct SelfSigned {
+    pub cert_pem: String,
+    pub key_pem: String,
+}
+impl SelfSigned {
+    pub fn generate() -> Self {
+        let cert = rcgen::generate_simple_self_signed(vec![
+            "localhost".to_string(),
+            "127.0.0.1".to_string(),
+        ])
+        .unwrap();
+        Self {
+            cert_pem: cert.cert.pem(),
+            key_pem: cert.key_pair.serialize_pem(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub method: String,
+    pub path: String,
+    pub query: String,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+impl MockRequest {
+    pub fn query_param(&self, key: &str) -> Option<String> {
+        url::form_urlencoded::parse(self.query.as_bytes())
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.to_string())
+    }
+    pub fn is_websocket(&self) -> bool {
+        self.headers
+            .get("upgrade")
+            .is_some_and(|upgrade| upgrade.eq_ignore_ascii_case("websocket"))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    pub status: u16,
+    pub body: String,
+}
+impl MockResponse {
+    pub fn ok(body: impl Into<String>) -> Self {
+        Self {
+            status: 200,
+            body: body.into(),
+        }
+    }
+    pub fn json(body: &serde_json::Value) -> Self {
+        Self::ok(body.to_string())
+    }
+    pub fn error(status: u16, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: body.into(),
+        }
+    }
+    /// Error in LND's REST shape, `{"code", "message", "details"}`.
+    pub fn lnd_error(status: u16, code: i32, message: &str) -> Self {
+        Self::error(
+            status,
+            serde_json::json!({ "code": code, "message": message, "details": [] }).to_string(),
+        )
+    }
+}
+
+/// Answers the requests received by a [`MockServer`].
+pub trait MockHandler: Send + Sync + 'static {
+    fn http(&self, request: MockRequest) -> MockResponse;
+    /// Drives an upgraded websocket connection. Closes it right away by default.
+    fn websocket(&self, _request: MockRequest, _socket: MockWebsocket) -> BoxFuture<'static, ()> {
+        Box::pin(async {})
+    }
+}
+impl<F> MockHandler for F
+where
+    F: Fn(MockRequest) -> MockResponse + Send + Sync + 'static,
+{
+    fn http(&self, request: MockRequest) -> MockResponse {
+        self(request)
+    }
+}
+
+/// HTTPS and websocket server with a freshly generated self-signed certificate.
+pub struct MockServer {
+    pub port: u16,
+    pub cert: SelfSigned,
+}
+impl MockServer {
+    pub async fn start(handler: impl MockHandler) -> Self {
+        Self::start_with_cert(SelfSigned::generate(), handler).await
+    }
+    pub async fn start_with_cert(cert: SelfSigned, handler: impl MockHandler) -> Self {
+        let handler: Arc<dyn MockHandler> = Arc::new(handler);
+        let identity =
+            native_tls::Identity::from_pkcs8(cert.cert_pem.as_bytes(), cert.key_pem.as_bytes())
+                .unwrap();
+        let acceptor = tokio_native_tls::TlsAcceptor::from(
+            native_tls::TlsAcceptor::builder(identity).build().unwrap(),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                let handler = handler.clone();
+                tokio::spawn(async move {
+                    let Ok(mut stream) = acceptor.accept(stream).await else {
+                        return;
+                    };
+                    let Some(request) = read_request(&mut stream).await else {
+                        return;
+                    };
+                    if request.is_websocket() {
+                        let key = request
+                            .headers
+                            .get("sec-websocket-key")
+                            .cloned()
+                            .unwrap_or_default();
+                        let response = format!(
+                            "HTTP/1.1 101 Switching Protocols\r\nConnection: Upgrade\r\nUpgrade: websocket\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+                            derive_accept_key(key.as_bytes())
+                        );
+                        if stream.write_all(response.as_bytes()).await.is_err() {
+                            return;
+                        }
+                        let socket =
+                            WebSocketStream::from_raw_socket(stream, Role::Server, None).await;
+                        handler.websocket(request, socket).await;
+                        return;
+                    }
+                    let response = handler.http(request);
+                    let response = format!(
+                        "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        response.status,
+                        response.body.len(),
+                        response.body
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                    let _ = stream.shutdown().await;
+                });
+            }
+        });
+        Self { port, cert }
+    }
+    pub fn tls(&self) -> LndTlsConfig {
+        LndTlsConfig::pinned(self.cert.cert_pem.as_bytes())
+    }
+    /// Client pinned to the server certificate and using [`MOCK_MACAROON`].
+    pub fn client(&self) -> LightningClient {
+        LightningClient::builder()
+            .host("localhost")
+            .port(self.port)
+            .macaroon_hex(MOCK_MACAROON)
+            .tls(self.tls())
+            .build()
+            .unwrap()
+    }
+}
+
+async fn read_request<S>(stream: &mut S) -> Option<MockRequest>
+where
+    S: AsyncReadExt + Unpin,
+{
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let head_len = loop {
+        let read = stream.read(&mut chunk).await.ok()?;
+        if read == 0 {
+            return None;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+        if let Some(position) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break position + 4;
+        }
+    };
+    let mut headers = [httparse::EMPTY_HEADER; 32];
+    let mut request = httparse::Request::new(&mut headers);
+    request.parse(&buffer[..head_len]).ok()?;
+    let method = request.method?.to_string();
+    let (path, query) = match request.path?.split_once('?') {
+        Some((path, query)) => (path.to_string(), query.to_string()),
+        None => (request.path?.to_string(), String::new()),
+    };
+    let headers = request
+        .headers
+        .iter()
+        .map(|header| {
+            (
+                header.name.to_lowercase(),
+                String::from_utf8_lossy(header.value).to_string(),
+            )
+        })
+        .collect::<HashMap<_, _>>();
+    let content_length = headers
+        .get("content-length")
+        .and_then(|length| length.parse::<usize>().ok())
+        .unwrap_or_default();
+    let mut body = buffer[head_len..].to_vec();
+    while body.len() < content_length {
+        let read = stream.read(&mut chunk).await.ok()?;
+        if read == 0 {
+            break;
+        }
+        body.extend_from_slice(&chunk[..read]);
+    }
+    Some(MockRequest {
+        method,
+        path,
+        query,
+        headers,
+        body: String::from_utf8_lossy(&body).to_string(),
+    })
+}